@@ -0,0 +1,129 @@
+use crate::main_menu::MainMenuPlugin;
+use crate::results_screen::ResultsScreenPlugin;
+use crate::tower_layout::TowerLayoutHandle;
+use crate::GameplayStateSubstates;
+use bevy::asset::LoadState;
+use bevy::prelude::*;
+
+pub struct AppStatePlugin;
+
+// The top level flow of the app, the gameplay substates only advance while in "Gameplay"
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub enum AppState {
+    Boot,
+    MainMenu,
+    Leaderboard,
+    Gameplay,
+    Results,
+}
+
+// Renders the menus while there is no gameplay camera
+#[derive(Component)]
+struct MenuCamera;
+
+// Assets that are loaded before the main menu is shown
+#[derive(Resource, Default)]
+struct BootAssets(Vec<HandleUntyped>);
+
+const BOOT_ASSET_PATHS: [&str; 8] = [
+    "papercut.ttf",
+    "PlayerTexture.png",
+    "PlayerSheet.png",
+    "player.animations.ron",
+    "PlatformTexture.png",
+    "PlatformTexture2.png",
+    "PlatformSurfaces.png",
+    "BackgroundTexture2.png",
+];
+
+impl Plugin for AppStatePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<BootAssets>()
+            .add_state(AppState::Boot)
+            .add_plugin(MainMenuPlugin)
+            .add_plugin(ResultsScreenPlugin)
+            .add_startup_system(spawn_menu_camera_system)
+            .add_system_set(
+                SystemSet::on_enter(AppState::Boot).with_system(load_boot_assets_system),
+            )
+            .add_system_set(SystemSet::on_update(AppState::Boot).with_system(finish_boot_system))
+            .add_system_set(
+                SystemSet::on_enter(AppState::MainMenu)
+                    .with_system(enable_menu_camera_system)
+                    .with_system(stop_gameplay_system),
+            )
+            .add_system_set(
+                SystemSet::on_enter(AppState::Leaderboard).with_system(enable_menu_camera_system),
+            )
+            .add_system_set(
+                SystemSet::on_enter(AppState::Gameplay)
+                    .with_system(disable_menu_camera_system)
+                    .with_system(start_gameplay_system),
+            )
+            .add_system_set(
+                SystemSet::on_enter(GameplayStateSubstates::PostGame)
+                    .with_system(show_results_system),
+            );
+    }
+}
+
+fn spawn_menu_camera_system(mut commands: Commands) {
+    commands.spawn((Camera2dBundle::default(), MenuCamera));
+}
+
+fn enable_menu_camera_system(mut camera_query: Query<&mut Camera, With<MenuCamera>>) {
+    for mut camera in camera_query.iter_mut() {
+        camera.is_active = true;
+    }
+}
+
+// The gameplay camera takes over, two active cameras would draw the UI twice
+fn disable_menu_camera_system(mut camera_query: Query<&mut Camera, With<MenuCamera>>) {
+    for mut camera in camera_query.iter_mut() {
+        camera.is_active = false;
+    }
+}
+
+fn load_boot_assets_system(mut boot_assets: ResMut<BootAssets>, asset_server: Res<AssetServer>) {
+    boot_assets.0 = BOOT_ASSET_PATHS
+        .iter()
+        .map(|path| asset_server.load_untyped(*path))
+        .collect();
+}
+
+// Moves on to the main menu once every boot asset and the tower layout have finished loading
+fn finish_boot_system(
+    mut app_state: ResMut<State<AppState>>,
+    boot_assets: Res<BootAssets>,
+    tower_layout_handle: Res<TowerLayoutHandle>,
+    asset_server: Res<AssetServer>,
+) {
+    let handle_ids = boot_assets
+        .0
+        .iter()
+        .map(|handle| handle.id)
+        .chain(tower_layout_handle.0.iter().map(|handle| handle.id()));
+
+    // Failed assets are reported by the asset server, they should not keep the game from starting
+    match asset_server.get_group_load_state(handle_ids) {
+        LoadState::Loaded | LoadState::Failed => {
+            let _ = app_state.set(AppState::MainMenu);
+        }
+        _ => {}
+    }
+}
+
+fn start_gameplay_system(mut gameplay_substate: ResMut<State<GameplayStateSubstates>>) {
+    let _ = gameplay_substate.set(GameplayStateSubstates::PreGame);
+}
+
+// Leaves whatever gameplay substate the run was in, which tears the gameplay entities down
+fn stop_gameplay_system(mut gameplay_substate: ResMut<State<GameplayStateSubstates>>) {
+    if *gameplay_substate.current() != GameplayStateSubstates::Inactive {
+        let _ = gameplay_substate.replace(GameplayStateSubstates::Inactive);
+    }
+}
+
+fn show_results_system(mut app_state: ResMut<State<AppState>>) {
+    let _ = app_state.set(AppState::Results);
+}
@@ -1,3 +1,4 @@
+use crate::settings::GameSettings;
 use bevy::prelude::*;
 use bevy_kira_audio::{prelude::*, Audio};
 use std::time::Duration;
@@ -6,19 +7,28 @@ pub struct AudioManagerPlugin;
 
 impl Plugin for AudioManagerPlugin {
     fn build(&self, app: &mut App) {
-        app.add_plugin(AudioPlugin).add_startup_system(play_music);
+        app.add_plugin(AudioPlugin)
+            .add_startup_system(play_music)
+            .add_system(apply_music_volume_system);
     }
 }
 
-fn play_music(asset_server: Res<AssetServer>, audio: Res<Audio>) {
+fn play_music(asset_server: Res<AssetServer>, audio: Res<Audio>, settings: Res<GameSettings>) {
     // This code plays a sound from the `asset_server` with a smooth fade-in effect and loops it.
-    // The sound is played at 95% volume.
+    // The sound is played at the volume chosen in the settings.
     audio
         .play(asset_server.load("Track4.wav"))
         .fade_in(AudioTween::new(
             Duration::from_secs(0),
             AudioEasing::OutPowi(0),
         ))
-        .with_volume(0.95)
+        .with_volume(settings.music_volume)
         .looped();
 }
+
+fn apply_music_volume_system(audio: Res<Audio>, settings: Res<GameSettings>) {
+    // Only touch the channel when the volume setting actually changed
+    if settings.is_changed() && !settings.is_added() {
+        audio.set_volume(settings.music_volume);
+    }
+}
@@ -0,0 +1,81 @@
+use crate::{GameplayCleanup, GameplayStateSubstates, PLAYER_SIZE};
+use bevy::prelude::*;
+
+pub struct CheckpointsPlugin;
+
+pub const STARTING_LIVES: u32 = 3;
+// How far above a checkpoint platform the player comes back
+pub const CHECKPOINT_RESPAWN_HEIGHT: f32 = PLAYER_SIZE;
+// Checkpoint platforms are tinted so they can be told apart before they are reached
+pub const CHECKPOINT_COLOR: Color = Color::rgb(0.6, 0.85, 1.0);
+
+// A floor the player comes back to after falling, as long as they have lives left
+#[derive(Component)]
+pub struct Checkpoint;
+
+// Falls the player can still take before the tower starts over, only used once a checkpoint is reached
+#[derive(Resource)]
+pub struct Lives(pub u32);
+
+#[derive(Component)]
+struct LivesUI;
+
+impl Plugin for CheckpointsPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(Lives(STARTING_LIVES))
+            .add_system_set(
+                SystemSet::on_enter(GameplayStateSubstates::PreGame)
+                    .with_system(reset_lives_system)
+                    .with_system(spawn_lives_ui_system),
+            )
+            .add_system_set(
+                SystemSet::on_update(GameplayStateSubstates::DuringGame)
+                    .with_system(update_lives_ui_system),
+            );
+    }
+}
+
+fn reset_lives_system(mut lives: ResMut<Lives>) {
+    lives.0 = STARTING_LIVES;
+}
+
+fn spawn_lives_ui_system(mut commands: Commands, asset_server: Res<AssetServer>) {
+    let font = asset_server.load("papercut.ttf");
+
+    // Sits under the points, it stays empty on towers without checkpoints
+    commands.spawn((
+        TextBundle::from_section(
+            "".to_string(),
+            TextStyle {
+                font,
+                font_size: 40.0,
+                color: Color::hex("FFFFFF7F").unwrap(),
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            position: UiRect {
+                top: Val::Percent(34.0),
+                left: Val::Percent(5.0),
+                ..default()
+            },
+            ..default()
+        }),
+        LivesUI,
+        GameplayCleanup,
+    ));
+}
+
+fn update_lives_ui_system(
+    mut text_query: Query<&mut Text, With<LivesUI>>,
+    checkpoint_query: Query<(), With<Checkpoint>>,
+    lives: Res<Lives>,
+) {
+    for mut text in text_query.iter_mut() {
+        text.sections[0].value = if checkpoint_query.is_empty() {
+            String::new()
+        } else {
+            format!("LIVES {}", lives.0)
+        };
+    }
+}
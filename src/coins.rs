@@ -0,0 +1,106 @@
+use crate::gameplay_state::FixedStepAppExt;
+use crate::{GameplayStateSubstates, Player, PLAYER_SIZE};
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+
+pub struct CoinsPlugin;
+
+const COIN_SIZE: f32 = PLAYER_SIZE * 0.4;
+// Past the bloom threshold of the camera, so coins glow
+const COIN_COLOR: Color = Color::rgb(2.2, 1.7, 0.3);
+// Drawn in front of the platform the coin floats over
+const COIN_Z: f32 = 0.1;
+
+// A coin floating above a floor, it is a child of the floor's platform so it moves along with it
+#[derive(Component)]
+pub struct Coin {
+    pub collected: bool,
+}
+
+// Sent every time the player touches a coin
+pub struct CoinCollectedEvent;
+
+impl Plugin for CoinsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_fixed_step_event::<CoinCollectedEvent>()
+            .add_fixed_step_system_set(SystemSet::new().with_system(coin_pickup_system))
+            .add_system_set(
+                SystemSet::on_update(GameplayStateSubstates::DuringGame)
+                    .with_system(coin_visibility_system),
+            );
+    }
+}
+
+impl Coin {
+    // Puts a collected coin back, used when the floor it floats over has to be reached again
+    pub fn restore(&mut self, commands: &mut Commands, coin_entity: Entity) {
+        if self.collected {
+            commands.entity(coin_entity).insert(coin_collider());
+        }
+        self.collected = false;
+    }
+}
+
+// Spawns a coin at `offset` from the platform it belongs to
+pub fn spawn_coin(parent: &mut ChildBuilder, offset: Vec2) {
+    parent.spawn((
+        SpriteBundle {
+            sprite: Sprite {
+                color: COIN_COLOR,
+                custom_size: Some(Vec2::splat(COIN_SIZE)),
+                ..default()
+            },
+            // Turned on its corner so it reads as a gem rather than another platform
+            transform: Transform::from_xyz(offset.x, offset.y, COIN_Z)
+                .with_rotation(Quat::from_rotation_z(std::f32::consts::FRAC_PI_4)),
+            ..default()
+        },
+        coin_collider(),
+        Sensor,
+        ActiveEvents::COLLISION_EVENTS,
+        Coin { collected: false },
+    ));
+}
+
+// The sensor every coin is spawned with, and that restored coins get back
+fn coin_collider() -> Collider {
+    Collider::ball(COIN_SIZE / 2.0)
+}
+
+// Picks up the coins the player's collider touches
+fn coin_pickup_system(
+    mut commands: Commands,
+    mut collision_events: EventReader<CollisionEvent>,
+    mut coin_query: Query<&mut Coin>,
+    player_query: Query<(), With<Player>>,
+    mut coin_collected_event: EventWriter<CoinCollectedEvent>,
+) {
+    for collision_event in collision_events.iter() {
+        let CollisionEvent::Started(first, second, _) = *collision_event else {
+            continue;
+        };
+
+        for (player, coin_entity) in [(first, second), (second, first)] {
+            if !player_query.contains(player) {
+                continue;
+            }
+            if let Ok(mut coin) = coin_query.get_mut(coin_entity) {
+                if coin.collected {
+                    continue;
+                }
+                coin.collected = true;
+                // The handle goes too, so the collider is set up again from scratch when it is restored
+                commands
+                    .entity(coin_entity)
+                    .remove::<(Collider, RapierColliderHandle)>();
+                coin_collected_event.send(CoinCollectedEvent);
+            }
+        }
+    }
+}
+
+fn coin_visibility_system(mut coin_query: Query<(&Coin, &mut Visibility)>) {
+    for (coin, mut visibility) in coin_query.iter_mut() {
+        visibility.is_visible = !coin.collected;
+    }
+}
@@ -0,0 +1,223 @@
+use crate::input_bindings::{InputAction, InputBindings};
+use crate::settings::SettingsMenuState;
+use crate::ui::{spawn_menu_button, spawn_menu_root, spawn_menu_row, spawn_menu_title};
+use bevy::prelude::*;
+
+pub struct ControlsMenuPlugin;
+
+#[derive(Component)]
+struct ControlsMenu;
+
+#[derive(Component, Clone, Copy)]
+enum ControlsButton {
+    Rebind(InputAction),
+    ResetDefaults,
+    Back,
+}
+
+// The action waiting for a key press, and the last key refused because another action uses it
+#[derive(Resource, Default)]
+struct Rebinding {
+    action: Option<InputAction>,
+    conflict: Option<(KeyCode, InputAction)>,
+}
+
+impl ControlsButton {
+    fn label(&self, input_bindings: &InputBindings, rebinding: &Rebinding) -> String {
+        match self {
+            ControlsButton::Rebind(action) => {
+                let keys = if rebinding.action == Some(*action) {
+                    match rebinding.conflict {
+                        Some((key, other_action)) => {
+                            format!("{} IS USED BY {}", key_name(key), other_action.label())
+                        }
+                        None => "PRESS A KEY".to_string(),
+                    }
+                } else {
+                    input_bindings
+                        .keys(*action)
+                        .iter()
+                        .map(|key| key_name(*key))
+                        .collect::<Vec<_>>()
+                        .join(" / ")
+                };
+                format!("{}: {}", action.label(), keys)
+            }
+            ControlsButton::ResetDefaults => "RESET".to_string(),
+            ControlsButton::Back => "BACK".to_string(),
+        }
+    }
+}
+
+fn key_name(key: KeyCode) -> String {
+    format!("{:?}", key).to_uppercase()
+}
+
+impl Plugin for ControlsMenuPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Rebinding>()
+            .add_system_set(
+                SystemSet::on_enter(SettingsMenuState::Controls)
+                    .with_system(spawn_controls_menu_system),
+            )
+            .add_system_set(
+                SystemSet::on_update(SettingsMenuState::Controls)
+                    .with_system(controls_button_system)
+                    .with_system(capture_key_system)
+                    .with_system(update_controls_labels_system),
+            )
+            .add_system_set(
+                SystemSet::on_exit(SettingsMenuState::Controls)
+                    .with_system(despawn_controls_menu_system),
+            );
+    }
+}
+
+fn spawn_controls_menu_system(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    input_bindings: Res<InputBindings>,
+    rebinding: Res<Rebinding>,
+) {
+    let font = asset_server.load("papercut.ttf");
+
+    let root = spawn_menu_root(&mut commands, ControlsMenu);
+    commands.entity(root).with_children(|parent| {
+        spawn_menu_title(parent, &font, "CONTROLS");
+        // The actions are split over two columns so the screen fits every one of them
+        spawn_menu_row(parent).with_children(|row| {
+            for actions in InputAction::ALL.chunks(4) {
+                row.spawn(NodeBundle {
+                    style: Style {
+                        flex_direction: FlexDirection::Column,
+                        ..default()
+                    },
+                    ..default()
+                })
+                .with_children(|column| {
+                    for action in actions {
+                        let button = ControlsButton::Rebind(*action);
+                        spawn_menu_button(
+                            column,
+                            &font,
+                            &button.label(&input_bindings, &rebinding),
+                            button,
+                        );
+                    }
+                });
+            }
+        });
+
+        spawn_menu_row(parent).with_children(|row| {
+            for button in [ControlsButton::ResetDefaults, ControlsButton::Back] {
+                spawn_menu_button(
+                    row,
+                    &font,
+                    &button.label(&input_bindings, &rebinding),
+                    button,
+                );
+            }
+        });
+    });
+}
+
+fn controls_button_system(
+    button_query: Query<(&Interaction, &ControlsButton), Changed<Interaction>>,
+    mut input_bindings: ResMut<InputBindings>,
+    mut rebinding: ResMut<Rebinding>,
+    mut settings_menu_state: ResMut<State<SettingsMenuState>>,
+) {
+    for (interaction, button) in button_query.iter() {
+        if *interaction != Interaction::Clicked {
+            continue;
+        }
+
+        match button {
+            ControlsButton::Rebind(action) => {
+                *rebinding = Rebinding {
+                    action: Some(*action),
+                    conflict: None,
+                };
+            }
+            ControlsButton::ResetDefaults => {
+                input_bindings.reset_to_defaults();
+                input_bindings.save();
+                *rebinding = Rebinding::default();
+            }
+            ControlsButton::Back => {
+                *rebinding = Rebinding::default();
+                let _ = settings_menu_state.pop();
+            }
+        }
+    }
+}
+
+// Binds the next key pressed to the action being rebound, unless another action already uses it
+fn capture_key_system(
+    mut keyboard_input: ResMut<Input<KeyCode>>,
+    mut action_input: ResMut<Input<InputAction>>,
+    mut input_bindings: ResMut<InputBindings>,
+    mut rebinding: ResMut<Rebinding>,
+    mut settings_menu_state: ResMut<State<SettingsMenuState>>,
+) {
+    // Escape cancels a rebind, or goes back to the settings menu when nothing is being rebound
+    if keyboard_input.clear_just_pressed(KeyCode::Escape) {
+        // Escape may also be bound to pause, which would resume the run underneath
+        action_input.clear_just_pressed(InputAction::Pause);
+        if rebinding.action.is_some() {
+            *rebinding = Rebinding::default();
+        } else {
+            let _ = settings_menu_state.pop();
+        }
+        return;
+    }
+
+    let Some(action) = rebinding.action else {
+        return;
+    };
+    let Some(key) = keyboard_input.get_just_pressed().next().copied() else {
+        return;
+    };
+    keyboard_input.clear_just_pressed(key);
+
+    match input_bindings.key_conflict(action, key) {
+        Some(other_action) => {
+            rebinding.conflict = Some((key, other_action));
+        }
+        None => {
+            input_bindings.rebind_key(action, key);
+            input_bindings.save();
+            *rebinding = Rebinding::default();
+        }
+    }
+}
+
+// Keeps the button labels in sync with the bindings and the key being waited on
+fn update_controls_labels_system(
+    button_query: Query<(&ControlsButton, &Children)>,
+    mut text_query: Query<&mut Text>,
+    input_bindings: Res<InputBindings>,
+    rebinding: Res<Rebinding>,
+) {
+    if !input_bindings.is_changed() && !rebinding.is_changed() {
+        return;
+    }
+
+    for (button, children) in button_query.iter() {
+        if let Ok(mut text) = text_query.get_mut(children[0]) {
+            text.sections[0].value = button.label(&input_bindings, &rebinding);
+        }
+    }
+}
+
+fn despawn_controls_menu_system(
+    mut commands: Commands,
+    mut rebinding: ResMut<Rebinding>,
+    controls_menu_query: Query<Entity, With<ControlsMenu>>,
+) {
+    *rebinding = Rebinding::default();
+
+    for entities in controls_menu_query.iter() {
+        commands.entity(entities).despawn_recursive();
+    }
+}
@@ -0,0 +1,156 @@
+use crate::difficulty::Difficulty;
+use crate::platforms::LevelSeed;
+use crate::player_input::InputSource;
+use crate::save_data::SaveData;
+use crate::{AppState, GameMode};
+use bevy::prelude::*;
+use chrono::Datelike;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+pub struct DailyPlugin;
+
+// A day on the local calendar
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct DailyDate {
+    pub year: i32,
+    pub month: u32,
+    pub day: u32,
+}
+
+// The tower of a day, everyone playing on the same date gets the same one
+#[derive(Resource, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DailyChallenge {
+    pub date: DailyDate,
+    pub seed: LevelSeed,
+    pub difficulty: Difficulty,
+}
+
+// Only the first daily challenge run of a day counts, the ones after it are practice
+#[derive(Resource, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum DailyAttempt {
+    #[default]
+    Scored,
+    Practice,
+}
+
+impl Plugin for DailyPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(DailyChallenge::for_date(DailyDate::today()))
+            .init_resource::<DailyAttempt>()
+            .add_system_set(
+                SystemSet::on_enter(AppState::Gameplay).with_system(start_daily_attempt_system),
+            );
+    }
+}
+
+impl DailyDate {
+    pub fn today() -> Self {
+        let today = chrono::Local::now().date_naive();
+        DailyDate {
+            year: today.year(),
+            month: today.month(),
+            day: today.day(),
+        }
+    }
+}
+
+impl fmt::Display for DailyDate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}-{:02}-{:02}", self.year, self.month, self.day)
+    }
+}
+
+impl DailyChallenge {
+    // Derives the challenge from nothing but the date, so it is the same on every machine without going online
+    pub fn for_date(date: DailyDate) -> Self {
+        let day_number = date.year as u64 * 10_000 + date.month as u64 * 100 + date.day as u64;
+        let seed = mix(day_number);
+
+        // Normal comes up on half the days, easy and hard on a quarter each
+        let difficulty = match mix(seed) % 4 {
+            0 => Difficulty::Easy,
+            3 => Difficulty::Hard,
+            _ => Difficulty::Normal,
+        };
+
+        DailyChallenge {
+            date,
+            seed: LevelSeed(seed),
+            difficulty,
+        }
+    }
+}
+
+// SplitMix64's finalizer, so neighbouring dates still get towers that have nothing in common
+fn mix(value: u64) -> u64 {
+    let mut mixed = value.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    mixed = (mixed ^ (mixed >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    mixed = (mixed ^ (mixed >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    mixed ^ (mixed >> 31)
+}
+
+// Uses up the day's scored run as soon as it starts, so quitting halfway does not give it back
+// Retrying from the results screen starts the run over and makes it practice
+fn start_daily_attempt_system(
+    mut daily_attempt: ResMut<DailyAttempt>,
+    mut save_data: ResMut<SaveData>,
+    daily_challenge: Res<DailyChallenge>,
+    game_mode: Res<GameMode>,
+    input_source: Res<InputSource>,
+) {
+    // A played back daily run was scored when it was first played
+    if *game_mode != GameMode::Daily || *input_source == InputSource::Replay {
+        return;
+    }
+
+    *daily_attempt = if save_data.daily_attempted(daily_challenge.date) {
+        DailyAttempt::Practice
+    } else {
+        save_data.record_daily_attempt(daily_challenge.date);
+        DailyAttempt::Scored
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(year: i32, month: u32, day: u32) -> DailyDate {
+        DailyDate { year, month, day }
+    }
+
+    #[test]
+    fn same_date_gives_same_challenge() {
+        assert_eq!(
+            DailyChallenge::for_date(date(2026, 10, 18)),
+            DailyChallenge::for_date(date(2026, 10, 18))
+        );
+    }
+
+    #[test]
+    fn neighbouring_dates_give_different_seeds() {
+        let pairs = [
+            (date(2026, 10, 18), date(2026, 10, 19)),
+            (date(2026, 10, 31), date(2026, 11, 1)),
+            (date(2026, 12, 31), date(2027, 1, 1)),
+        ];
+        for (first, second) in pairs {
+            assert_ne!(
+                DailyChallenge::for_date(first).seed,
+                DailyChallenge::for_date(second).seed,
+                "{} and {} share a seed",
+                first,
+                second
+            );
+        }
+    }
+
+    // Every player has to get the same tower on the same day, so the seed of a date must never change
+    #[test]
+    fn pinned_date_keeps_its_challenge() {
+        let challenge = DailyChallenge::for_date(date(2026, 10, 18));
+        assert_eq!(challenge.seed, LevelSeed(0xB071_EAD4_0873_8983));
+        assert_eq!(challenge.difficulty, Difficulty::Easy);
+    }
+}
@@ -0,0 +1,192 @@
+use crate::daily::DailyChallenge;
+use crate::settings::GameSettings;
+use crate::{AppState, GameMode};
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+pub struct DifficultyPlugin;
+
+// How hard generated towers are, picked in the settings menu and fixed for the length of a run
+#[derive(
+    Resource,
+    Serialize,
+    Deserialize,
+    Debug,
+    Default,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+)]
+pub enum Difficulty {
+    Easy,
+    #[default]
+    Normal,
+    Hard,
+}
+
+// A value that changes evenly from the bottom floor of the tower to the top one
+#[derive(Debug, Clone, Copy)]
+pub struct Ramp {
+    pub bottom: f32,
+    pub top: f32,
+}
+
+// How fast the rising hazard climbs, it waits out `delay` seconds and then speeds up until it
+// reaches `max_speed`, in pixels per second
+#[derive(Debug, Clone, Copy)]
+pub struct HazardCurve {
+    pub delay: f32,
+    pub start_speed: f32,
+    // Pixels per second gained every second
+    pub acceleration: f32,
+    pub max_speed: f32,
+}
+
+// How a generated tower gets harder on the way up
+#[derive(Debug, Clone, Copy)]
+pub struct DifficultyProfile {
+    // The gap between two floors, as a share of the window height
+    pub spacing: Ramp,
+    // The chance of a floor moving, and the range its speed is picked from
+    pub moving_chance: Ramp,
+    pub min_speed: Ramp,
+    pub max_speed: Ramp,
+    // Multiplies the width of the platforms
+    pub platform_width: Ramp,
+    // The chance of a floor crumbling or having a bouncy, ice or conveyor surface
+    pub special_chance: Ramp,
+    // Every this many floors is a checkpoint, `None` leaves the tower without any
+    pub checkpoint_interval: Option<u32>,
+    pub hazard: HazardCurve,
+}
+
+// The widest gap still within a full bounce, see `JUMP_FORCE`
+const MAX_SPACING: f32 = 1.0 / 4.2;
+
+const EASY: DifficultyProfile = DifficultyProfile {
+    spacing: Ramp::new(0.19, 0.21),
+    moving_chance: Ramp::new(0.2, 0.4),
+    min_speed: Ramp::new(60.0, 90.0),
+    max_speed: Ramp::new(110.0, 150.0),
+    platform_width: Ramp::new(1.25, 1.05),
+    special_chance: Ramp::new(0.0, 0.2),
+    checkpoint_interval: Some(5),
+    hazard: HazardCurve {
+        delay: 6.0,
+        start_speed: 12.0,
+        acceleration: 0.4,
+        max_speed: 40.0,
+    },
+};
+
+// The classic tower had a constant spacing of `MAX_SPACING`, even odds of moving and speeds of 100 to 200
+const NORMAL: DifficultyProfile = DifficultyProfile {
+    spacing: Ramp::new(0.21, MAX_SPACING),
+    moving_chance: Ramp::new(0.35, 0.65),
+    min_speed: Ramp::new(80.0, 120.0),
+    max_speed: Ramp::new(150.0, 220.0),
+    platform_width: Ramp::new(1.1, 0.9),
+    special_chance: Ramp::new(0.1, 0.35),
+    checkpoint_interval: Some(10),
+    hazard: HazardCurve {
+        delay: 4.0,
+        start_speed: 18.0,
+        acceleration: 0.6,
+        max_speed: 55.0,
+    },
+};
+
+const HARD: DifficultyProfile = DifficultyProfile {
+    spacing: Ramp::new(0.225, MAX_SPACING),
+    moving_chance: Ramp::new(0.5, 0.85),
+    min_speed: Ramp::new(120.0, 170.0),
+    max_speed: Ramp::new(200.0, 280.0),
+    platform_width: Ramp::new(1.0, 0.7),
+    special_chance: Ramp::new(0.2, 0.5),
+    checkpoint_interval: None,
+    hazard: HazardCurve {
+        delay: 3.0,
+        start_speed: 25.0,
+        acceleration: 0.9,
+        max_speed: 70.0,
+    },
+};
+
+impl Plugin for DifficultyPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Difficulty>().add_system_set(
+            SystemSet::on_enter(AppState::Gameplay).with_system(apply_difficulty_system),
+        );
+    }
+}
+
+impl Difficulty {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Difficulty::Easy => "EASY",
+            Difficulty::Normal => "NORMAL",
+            Difficulty::Hard => "HARD",
+        }
+    }
+
+    pub fn profile(&self) -> &'static DifficultyProfile {
+        match self {
+            Difficulty::Easy => &EASY,
+            Difficulty::Normal => &NORMAL,
+            Difficulty::Hard => &HARD,
+        }
+    }
+
+    // The difficulty a run is filed under, custom towers are built the same whatever the difficulty
+    // and the daily challenge picks its own, so all of their runs are filed together
+    pub fn of_run(self, game_mode: GameMode) -> Option<Difficulty> {
+        (game_mode != GameMode::CustomTower && game_mode != GameMode::Daily).then_some(self)
+    }
+
+    // The next preset in the settings menu, wrapping back around to the easiest
+    pub fn next(&self) -> Self {
+        match self {
+            Difficulty::Easy => Difficulty::Normal,
+            Difficulty::Normal => Difficulty::Hard,
+            Difficulty::Hard => Difficulty::Easy,
+        }
+    }
+}
+
+impl HazardCurve {
+    // The speed `elapsed` seconds into the climb
+    pub fn speed_at(&self, elapsed: f32) -> f32 {
+        if elapsed < self.delay {
+            return 0.0;
+        }
+        (self.start_speed + self.acceleration * (elapsed - self.delay)).min(self.max_speed)
+    }
+}
+
+impl Ramp {
+    pub const fn new(bottom: f32, top: f32) -> Self {
+        Ramp { bottom, top }
+    }
+
+    // `height` goes from 0.0 at the bottom of the tower to 1.0 at the top
+    pub fn at(&self, height: f32) -> f32 {
+        self.bottom + (self.top - self.bottom) * height.clamp(0.0, 1.0)
+    }
+}
+
+// A difficulty picked in the middle of a run only takes effect once the next one starts
+fn apply_difficulty_system(
+    mut difficulty: ResMut<Difficulty>,
+    settings: Res<GameSettings>,
+    game_mode: Res<GameMode>,
+    daily_challenge: Res<DailyChallenge>,
+) {
+    *difficulty = match *game_mode {
+        GameMode::Daily => daily_challenge.difficulty,
+        _ => settings.difficulty,
+    };
+}
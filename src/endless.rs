@@ -0,0 +1,184 @@
+use crate::difficulty::Difficulty;
+use crate::gameplay_state::{DeathLine, FixedStepAppExt};
+use crate::platforms::{
+    coin_rng, generate_reachable_floor, roll_coin, spawn_platform, starting_floor, LevelSeed,
+    Platform,
+};
+use crate::tower_layout::FloorLayout;
+use crate::{GameMode, GameplayStateSubstates, Player, WindowDimensions};
+use bevy::prelude::*;
+use rand::{rngs::StdRng, SeedableRng};
+use std::collections::VecDeque;
+
+pub struct EndlessPlugin;
+
+// The number of floors generated at a time
+const CHUNK_FLOORS: u32 = 10;
+// Floors are generated this many window heights above the player, well out of sight of the camera following them
+const GENERATE_AHEAD: f32 = 1.5;
+// Chunks are despawned once they are this many window heights below the death line
+const DESPAWN_BELOW: f32 = 1.0;
+// An endless tower gets as hard as the top of a standard one after this many floors, and stays that way
+const RAMP_FLOORS: f32 = 100.0;
+// How far below the highest point of the run the player can fall before it is over
+const FALL_DISTANCE: f32 = 400.0;
+
+// The chunk an endless platform was generated in, the whole chunk is despawned at once
+#[derive(Component)]
+struct EndlessChunk(u32);
+
+// The part of the endless tower generated so far
+#[derive(Resource)]
+struct EndlessTower {
+    rng: StdRng,
+    coin_rng: StdRng,
+    // The highest floor so far, the next one has to be reachable from it
+    top_floor: FloorLayout,
+    floor_count: u32,
+    chunk_count: u32,
+    // The chunks still standing and the height of their highest floor, lowest first
+    chunk_tops: VecDeque<(u32, f32)>,
+}
+
+impl Plugin for EndlessPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(
+            SystemSet::on_enter(GameplayStateSubstates::PreGame)
+                .with_system(remove_endless_tower_system),
+        )
+        .add_system_set(
+            SystemSet::on_update(GameplayStateSubstates::PreGame)
+                .with_system(spawn_endless_tower_system),
+        )
+        .add_fixed_step_system_set(
+            SystemSet::new()
+                .with_system(stream_endless_tower_system)
+                .with_system(raise_death_line_system),
+        );
+    }
+}
+
+impl EndlessTower {
+    // Generates and spawns the next chunk of floors on top of the tower
+    fn spawn_chunk(
+        &mut self,
+        commands: &mut Commands,
+        asset_server: &AssetServer,
+        window: &WindowDimensions,
+        difficulty: Difficulty,
+    ) {
+        let chunk = self.chunk_count;
+        for _ in 0..CHUNK_FLOORS {
+            self.floor_count += 1;
+            let height = self.floor_count as f32 / RAMP_FLOORS;
+            let mut floor = generate_reachable_floor(
+                &mut self.rng,
+                window,
+                difficulty.profile(),
+                height,
+                &self.top_floor,
+            );
+            floor.coin = roll_coin(&mut self.coin_rng);
+            let platform = spawn_platform(
+                commands,
+                asset_server,
+                window,
+                self.floor_count as usize,
+                &floor,
+            );
+            commands.entity(platform).insert(EndlessChunk(chunk));
+            self.top_floor = floor;
+        }
+
+        self.chunk_tops.push_back((chunk, self.top_floor.y));
+        self.chunk_count += 1;
+    }
+}
+
+// The tower of the previous run is gone, so a standard run does not keep streaming it in
+fn remove_endless_tower_system(mut commands: Commands) {
+    commands.remove_resource::<EndlessTower>();
+}
+
+// Spawns the starting platform and the first chunk, every chunk after it is rolled from the same level seed
+fn spawn_endless_tower_system(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    window: Res<WindowDimensions>,
+    level_seed: Res<LevelSeed>,
+    difficulty: Res<Difficulty>,
+    game_mode: Res<GameMode>,
+    platform_query: Query<(), With<Platform>>,
+) {
+    if *game_mode != GameMode::Endless || !platform_query.is_empty() {
+        return;
+    }
+
+    info!(
+        "Generating endless {:?} tower with seed {}",
+        *difficulty, level_seed.0
+    );
+    let top_floor = starting_floor(&window);
+    let platform = spawn_platform(&mut commands, &asset_server, &window, 0, &top_floor);
+    commands.entity(platform).insert(EndlessChunk(0));
+
+    let mut endless_tower = EndlessTower {
+        rng: StdRng::seed_from_u64(level_seed.0),
+        coin_rng: coin_rng(*level_seed),
+        top_floor,
+        floor_count: 0,
+        chunk_count: 0,
+        chunk_tops: VecDeque::new(),
+    };
+    endless_tower.spawn_chunk(&mut commands, &asset_server, &window, *difficulty);
+    commands.insert_resource(endless_tower);
+}
+
+// Keeps the tower growing ahead of the player and takes away the chunks left far below them
+#[allow(clippy::too_many_arguments)]
+fn stream_endless_tower_system(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    window: Res<WindowDimensions>,
+    difficulty: Res<Difficulty>,
+    death_line: Res<DeathLine>,
+    endless_tower: Option<ResMut<EndlessTower>>,
+    player_query: Query<&Transform, With<Player>>,
+    chunk_query: Query<(Entity, &EndlessChunk)>,
+) {
+    let Some(mut endless_tower) = endless_tower else {
+        return;
+    };
+
+    let player_y = player_query.single().translation.y;
+    while endless_tower.top_floor.y < player_y + window.height * GENERATE_AHEAD {
+        endless_tower.spawn_chunk(&mut commands, &asset_server, &window, *difficulty);
+    }
+
+    while let Some(&(chunk, top)) = endless_tower.chunk_tops.front() {
+        if top > death_line.0 - window.height * DESPAWN_BELOW {
+            break;
+        }
+
+        for (platform_entity, platform_chunk) in chunk_query.iter() {
+            if platform_chunk.0 == chunk {
+                commands.entity(platform_entity).despawn_recursive();
+            }
+        }
+        endless_tower.chunk_tops.pop_front();
+    }
+}
+
+// The death line follows the player up, so falling too far back down ends the run
+fn raise_death_line_system(
+    mut death_line: ResMut<DeathLine>,
+    player_query: Query<&Transform, With<Player>>,
+    game_mode: Res<GameMode>,
+) {
+    if *game_mode != GameMode::Endless {
+        return;
+    }
+
+    let player_y = player_query.single().translation.y;
+    death_line.0 = death_line.0.max(player_y - FALL_DISTANCE);
+}
@@ -1,4 +1,4 @@
-use crate::{window_manager::WindowDimensions, GameplayStateSubstates, Player};
+use crate::{window_manager::WindowDimensions, GameplayCleanup, GameplayStateSubstates, Player};
 use bevy::time::Stopwatch;
 use bevy::{core_pipeline::bloom::BloomSettings, prelude::*};
 use bevy_rapier2d::prelude::*;
@@ -63,6 +63,7 @@ fn spawn_camera_system(mut commands: Commands) {
             stationary_zoom_amt: 0.8,
             motion_zoom_amt: 1.1,
         },
+        GameplayCleanup,
     ));
 }
 
@@ -84,9 +85,11 @@ fn spawn_background_system(
             ..Default::default()
         },
         Background,
+        GameplayCleanup,
     ));
 }
 
+#[allow(clippy::type_complexity)]
 fn follow_player_system(
     mut camera_query: Query<((&mut Transform, &PlayerCamera), With<PlayerCamera>)>,
     mut background_query: Query<
@@ -98,7 +101,7 @@ fn follow_player_system(
 ) {
     // This code updates the position of a camera entity and a background sprite entity.
     // The camera follows the player's vertical position, and the background sprite is positioned at the same vertical position as the camera.
-    
+
     let (mut camera, _camera_object) = camera_query.single_mut();
     let mut background = background_query.single_mut();
     let player = player_query.single();
@@ -131,12 +134,11 @@ fn camera_zoom_system(
         game_stopwatch.0.reset();
     }
 
-    let target_scale;
-    if game_stopwatch.0.elapsed_secs() > 0.45 {
-        target_scale = camera_obj.stationary_zoom_amt;
+    let target_scale = if game_stopwatch.0.elapsed_secs() > 0.45 {
+        camera_obj.stationary_zoom_amt
     } else {
-        target_scale = camera_obj.motion_zoom_amt;
-    }
+        camera_obj.motion_zoom_amt
+    };
 
     camera_proj.scale = camera_proj
         .scale
@@ -1,7 +1,13 @@
+use crate::difficulty::Difficulty;
+use crate::gameplay_state::{FixedStepAppExt, FIXED_TIMESTEP};
+use crate::platforms::{LevelSeed, Platform};
 use crate::player::Player;
-use crate::{GameplayStateSubstates, WINDOW_HEIGHT, WINDOW_WIDTH};
+use crate::save_data::{RunTower, SaveData};
+use crate::{GameMode, GameplayCleanup, GameplayStateSubstates, WINDOW_HEIGHT, WINDOW_WIDTH};
 use bevy::prelude::*;
 use bevy::time::Stopwatch;
+use std::collections::BTreeMap;
+use std::time::Duration;
 
 pub struct GameTimerPlugin;
 
@@ -18,7 +24,22 @@ struct GameStopwatch(Stopwatch);
 struct CurrentGameTime(f32);
 
 #[derive(Resource)]
-pub struct FinalGameTime(f32);
+pub struct FinalGameTime(pub f32);
+
+// The run time at which each floor was first landed on, keyed by the floor's place in the tower
+#[derive(Resource, Default)]
+pub struct RunSplits(pub BTreeMap<usize, f32>);
+
+// Shows how the latest split compares to the best split for that floor
+#[derive(Component)]
+struct SplitDeltaUI;
+
+// How long a split delta stays on screen
+#[derive(Resource)]
+struct SplitDeltaDisplayTimer(Timer);
+
+const SPLIT_AHEAD_COLOR: Color = Color::rgb(0.1, 0.75, 0.3);
+const SPLIT_BEHIND_COLOR: Color = Color::rgb(0.85, 0.2, 0.15);
 
 impl Plugin for GameTimerPlugin {
     fn build(&self, app: &mut App) {
@@ -30,6 +51,11 @@ impl Plugin for GameTimerPlugin {
             )))
             .insert_resource(CurrentGameTime(0.0))
             .insert_resource(FinalGameTime(0.0))
+            .init_resource::<RunSplits>()
+            .insert_resource(SplitDeltaDisplayTimer(Timer::from_seconds(
+                2.5,
+                TimerMode::Once,
+            )))
             .add_system_set(
                 SystemSet::on_enter(GameplayStateSubstates::PreGame)
                     .with_system(show_timer_ui_system),
@@ -37,9 +63,20 @@ impl Plugin for GameTimerPlugin {
             .add_system_set(
                 SystemSet::on_exit(GameplayStateSubstates::PreGame).with_system(start_timer_system),
             )
+            .add_fixed_step_system_set(
+                SystemSet::new()
+                    .with_system(update_timer_system)
+                    .with_system(record_splits_system),
+            )
             .add_system_set(
                 SystemSet::on_update(GameplayStateSubstates::DuringGame)
-                    .with_system(update_timer_system),
+                    .with_system(update_split_delta_ui_system),
+            )
+            .add_system_set(
+                SystemSet::on_enter(GameplayStateSubstates::Paused).with_system(pause_timer_system),
+            )
+            .add_system_set(
+                SystemSet::on_exit(GameplayStateSubstates::Paused).with_system(start_timer_system),
             )
             .add_system_set(
                 SystemSet::on_enter(GameplayStateSubstates::PostGame)
@@ -55,11 +92,14 @@ impl Plugin for GameTimerPlugin {
 fn show_timer_ui_system(
     mut commands: Commands,
     mut game_stopwatch: ResMut<GameStopwatch>,
+    mut run_splits: ResMut<RunSplits>,
     asset_server: Res<AssetServer>,
     game_timer_ui_offset: Res<GameTimerUIOffset>,
 ) {
-    // The game's stopwatch is paused to prevent timer autostart
+    // The game's stopwatch is reset for the new run and paused to prevent timer autostart
+    game_stopwatch.0.reset();
     game_stopwatch.0.pause();
+    run_splits.0.clear();
 
     // Load the font from the asset server
     let font = asset_server.load("papercut.ttf");
@@ -79,6 +119,24 @@ fn show_timer_ui_system(
     // Set the text alignment
     let text_alignment = TextAlignment::CENTER_LEFT;
 
+    // Spawn the split delta text below the timer, it stays empty until a split is recorded
+    commands.spawn((
+        Text2dBundle {
+            text: Text::from_section(
+                "",
+                TextStyle {
+                    font_size: 40.0,
+                    ..text_style.clone()
+                },
+            )
+            .with_alignment(text_alignment),
+            transform: Transform::from_translation(game_timer_ui_offset.0),
+            ..default()
+        },
+        SplitDeltaUI,
+        GameplayCleanup,
+    ));
+
     // Spawn the text entity with the defined style and alignment
     commands.spawn((
         Text2dBundle {
@@ -92,6 +150,7 @@ fn show_timer_ui_system(
             ..default()
         },
         GameTimerUI,
+        GameplayCleanup,
     ));
 }
 
@@ -99,16 +158,21 @@ fn start_timer_system(mut game_stopwatch: ResMut<GameStopwatch>) {
     game_stopwatch.0.unpause();
 }
 
+fn pause_timer_system(mut game_stopwatch: ResMut<GameStopwatch>) {
+    game_stopwatch.0.pause();
+}
+
 fn update_timer_system(
     mut game_timer_ui_query: Query<(&mut Text, &mut Transform), With<GameTimerUI>>,
     player_query: Query<&Transform, (With<Player>, Without<GameTimerUI>)>,
     mut game_stopwatch: ResMut<GameStopwatch>,
     mut current_game_time: ResMut<CurrentGameTime>,
     game_timer_ui_offset: Res<GameTimerUIOffset>,
-    time: Res<Time>,
 ) {
-    // Update the elapsed time of the game stopwatch
-    game_stopwatch.0.tick(time.delta());
+    // Update the elapsed time of the game stopwatch, it counts simulated time so replays keep their times
+    game_stopwatch
+        .0
+        .tick(Duration::from_secs_f32(FIXED_TIMESTEP));
     // Save the current elapsed time in a variable
     current_game_time.0 = game_stopwatch.0.elapsed_secs();
 
@@ -125,6 +189,74 @@ fn update_timer_system(
     text_transform.translation = game_timer_ui_offset.0 + player_transform.translation;
 }
 
+// Records a split the first time each floor is landed on, and compares it to the best run's split for that floor
+#[allow(clippy::too_many_arguments)]
+fn record_splits_system(
+    mut split_delta_ui_query: Query<&mut Text, With<SplitDeltaUI>>,
+    platform_query: Query<&Platform>,
+    mut run_splits: ResMut<RunSplits>,
+    mut split_delta_display_timer: ResMut<SplitDeltaDisplayTimer>,
+    current_game_time: Res<CurrentGameTime>,
+    save_data: Res<SaveData>,
+    game_mode: Res<GameMode>,
+    difficulty: Res<Difficulty>,
+    level_seed: Res<LevelSeed>,
+) {
+    let category = (*game_mode, difficulty.of_run(*game_mode));
+    let tower = RunTower::of_run(*game_mode, &level_seed);
+
+    for platform in platform_query.iter() {
+        // The floors are counted again after the player falls, so their splits start over with them
+        if !platform.already_collided {
+            run_splits.0.remove(&platform.floor);
+            continue;
+        }
+        if run_splits.0.contains_key(&platform.floor) {
+            continue;
+        }
+
+        let split = current_game_time.0;
+        run_splits.0.insert(platform.floor, split);
+
+        // Only show a delta once the best run has a split for the same floor to compare against
+        if let Some(best_split) = save_data.best_split(category, &tower, platform.floor) {
+            let delta = split - best_split;
+            let mut text = split_delta_ui_query.single_mut();
+            text.sections[0].value = format!("{:+.2}", delta);
+            text.sections[0].style.color = if delta <= 0.0 {
+                SPLIT_AHEAD_COLOR
+            } else {
+                SPLIT_BEHIND_COLOR
+            };
+            split_delta_display_timer.0.reset();
+        }
+    }
+}
+
+fn update_split_delta_ui_system(
+    mut split_delta_ui_query: Query<(&mut Text, &mut Transform), With<SplitDeltaUI>>,
+    player_query: Query<&Transform, (With<Player>, Without<SplitDeltaUI>)>,
+    mut split_delta_display_timer: ResMut<SplitDeltaDisplayTimer>,
+    game_timer_ui_offset: Res<GameTimerUIOffset>,
+    time: Res<Time>,
+) {
+    let (mut text, mut text_transform) = split_delta_ui_query.single_mut();
+
+    // Clear the delta once it has been on screen long enough
+    if split_delta_display_timer
+        .0
+        .tick(time.delta())
+        .just_finished()
+    {
+        text.sections[0].value = "".to_string();
+    }
+
+    // Keep the delta just below the timer UI element
+    let player_transform = player_query.single();
+    text_transform.translation =
+        game_timer_ui_offset.0 + player_transform.translation + Vec3::new(0.0, -60.0, 0.0);
+}
+
 fn reset_and_save_timer_system(
     mut game_stopwatch: ResMut<GameStopwatch>,
     mut final_game_time: ResMut<FinalGameTime>,
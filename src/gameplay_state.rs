@@ -1,94 +1,383 @@
+use crate::checkpoints::{Checkpoint, Lives, CHECKPOINT_RESPAWN_HEIGHT, STARTING_LIVES};
+use crate::one_way_platforms::{OneWayPlatformHookData, OneWayPlatformHooks};
+use crate::platforms::{CrumblingPlatform, Surface};
 use crate::PLAYER_SIZE;
-use crate::{game_camera, game_timer, platforms, player, ui};
+use crate::{
+    checkpoints, coins, daily, game_camera, game_timer, ghost, hazard, pause_menu, platforms,
+    player, player_input, replay, scoring, ui,
+};
 use crate::{Platform, Player};
+use bevy::ecs::event::{Event, Events};
+use bevy::ecs::schedule::ShouldRun;
 use bevy::prelude::*;
 use bevy_rapier2d::prelude::*;
+use serde::{Deserialize, Serialize};
 
 pub struct GameplayStatePlugin;
 
+type PhysicsPlugin = RapierPhysicsPlugin<OneWayPlatformHookData>;
+
+// Every fixed step advances the run by exactly this much, so the same inputs always play out the same way
+pub const FIXED_TIMESTEP: f32 = 1.0 / 60.0;
+
+// A long frame, like the one that loads the tower, is only caught up on this many fixed steps
+const MAX_FIXED_STEPS_PER_FRAME: f32 = 8.0;
+
+// Downwards, in pixels per second squared
+pub const GRAVITY: f32 = -275.0;
+
+// Where the death line starts every run
+pub const DEATH_LINE: f32 = -400.0;
+
 #[derive(Debug, Clone, Eq, PartialEq, Hash)]
 pub enum GameplayStateSubstates {
+    // No run is in progress, the app is in a menu
+    Inactive,
     PreGame,
     DuringGame,
+    // Pushed on top of "DuringGame" so the run can be resumed where it was left
+    Paused,
     PostGame,
 }
 
+// Which kind of run is being played, each mode keeps its own leaderboard
+#[derive(
+    Resource, Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash,
+)]
+pub enum GameMode {
+    Standard,
+    // A tower loaded from a layout file with `--level`
+    CustomTower,
+    // A generated tower without a top, the run lasts until the player falls
+    Endless,
+    // The tower of the day, built from the date
+    Daily,
+}
+
 #[derive(Resource)]
 pub struct Gravity(f32);
 
+// Falling below this height fails the attempt, endless runs raise it as the player climbs
+#[derive(Resource)]
+pub struct DeathLine(pub f32);
+
+// Marks entities that belong to a single run and are despawned when it ends
+#[derive(Component)]
+pub struct GameplayCleanup;
+
 pub struct TopFloorReachedEvent;
 pub struct DeathRegionReachedEvent;
+// Sent with the platform of a floor the player lands on for the first time
+pub struct FloorReachedEvent(pub Entity);
+// Sent by the restart key, the tower starts over whatever checkpoint was reached
+pub struct RestartRequestedEvent;
+// Sent with where the player was put back after a failed attempt
+pub struct PlayerRespawnedEvent(pub Vec3);
+
+// Runs the gameplay systems and the physics once for every FIXED_TIMESTEP of time that has passed,
+// so the run plays out the same at any frame rate
+#[derive(StageLabel)]
+pub struct FixedStepStage;
+
+// The stages of a single fixed step, the physics stages of Rapier run after them
+#[derive(StageLabel)]
+enum FixedStepStages {
+    // Clears the events of the run, so they last two steps instead of two frames
+    Events,
+    Gameplay,
+}
+
+pub trait FixedStepAppExt {
+    // Adds systems that run on every fixed step while the run is being played
+    fn add_fixed_step_system_set(&mut self, system_set: SystemSet) -> &mut Self;
+    // Adds an event sent and read by fixed step systems
+    fn add_fixed_step_event<T: Event>(&mut self) -> &mut Self;
+}
+
+impl FixedStepAppExt for App {
+    fn add_fixed_step_system_set(&mut self, system_set: SystemSet) -> &mut Self {
+        self.stage(FixedStepStage, |schedule: &mut Schedule| {
+            schedule.add_system_set_to_stage(
+                FixedStepStages::Gameplay,
+                system_set.with_run_criteria(during_game_criteria),
+            )
+        })
+    }
+
+    fn add_fixed_step_event<T: Event>(&mut self) -> &mut Self {
+        if self.world.contains_resource::<Events<T>>() {
+            return self;
+        }
+
+        self.init_resource::<Events<T>>()
+            .stage(FixedStepStage, |schedule: &mut Schedule| {
+                schedule.add_system_to_stage(FixedStepStages::Events, Events::<T>::update_system)
+            })
+    }
+}
 
 impl Plugin for GameplayStatePlugin {
     fn build(&self, app: &mut App) {
-        app.add_event::<TopFloorReachedEvent>()
-            .add_event::<DeathRegionReachedEvent>()
-            .insert_resource(Gravity(-275.0))
-            .add_plugin(RapierPhysicsPlugin::<NoUserData>::pixels_per_meter(550.0))
+        app.add_stage_after(CoreStage::Update, FixedStepStage, fixed_step_schedule())
+            .add_stage_before(
+                CoreStage::Last,
+                PhysicsStages::DetectDespawn,
+                SystemStage::parallel()
+                    .with_system_set(PhysicsPlugin::get_systems(PhysicsStages::DetectDespawn)),
+            );
+
+        app.add_fixed_step_event::<TopFloorReachedEvent>()
+            .add_fixed_step_event::<DeathRegionReachedEvent>()
+            .add_fixed_step_event::<FloorReachedEvent>()
+            .add_fixed_step_event::<RestartRequestedEvent>()
+            .add_fixed_step_event::<PlayerRespawnedEvent>()
+            .insert_resource(Gravity(GRAVITY))
+            .insert_resource(DeathLine(DEATH_LINE))
+            .insert_resource(GameMode::Standard)
+            .insert_resource(PhysicsHooksWithQueryResource::<OneWayPlatformHookData>(
+                Box::new(OneWayPlatformHooks),
+            ))
+            // The physics stages are stepped along with the gameplay systems in the fixed step stage
+            .add_plugin(PhysicsPlugin::pixels_per_meter(550.0).with_default_system_setup(false))
             // .add_plugin(RapierDebugRenderPlugin::default())
+            .add_plugin(player_input::PlayerInputPlugin)
+            .add_plugin(replay::ReplayPlugin)
+            .add_plugin(ghost::GhostPlugin)
+            .add_plugin(daily::DailyPlugin)
             .add_plugin(player::PlayerPlugin)
             .add_plugin(game_camera::GameCameraPlugin)
             .add_plugin(platforms::PlatformsPlugin)
+            .add_plugin(checkpoints::CheckpointsPlugin)
+            .add_plugin(hazard::HazardPlugin)
+            .add_plugin(coins::CoinsPlugin)
+            .add_plugin(scoring::ScoringPlugin)
             .add_plugin(ui::UIPlugin)
             .add_plugin(game_timer::GameTimerPlugin)
+            .add_plugin(pause_menu::PauseMenuPlugin)
+            .add_system_set(
+                SystemSet::on_enter(GameplayStateSubstates::Inactive)
+                    .with_system(despawn_gameplay_entities_system),
+            )
             .add_system_set(
                 SystemSet::on_enter(GameplayStateSubstates::PreGame)
-                    .with_system(initilizate_physics_system),
+                    .with_system(initilizate_physics_system)
+                    .with_system(reset_death_line_system)
+                    .with_system(despawn_gameplay_entities_system),
             )
             .add_system_set(
                 SystemSet::on_update(GameplayStateSubstates::PreGame)
                     .with_system(switch_gameplay_substates_system),
             )
             .add_system_set(
-                SystemSet::on_update(GameplayStateSubstates::DuringGame)
-                    .with_system(game_completion_system),
+                SystemSet::on_enter(GameplayStateSubstates::DuringGame)
+                    .with_system(start_physics_system),
+            )
+            .add_fixed_step_system_set(SystemSet::new().with_system(game_completion_system))
+            .add_system_set(
+                SystemSet::on_enter(GameplayStateSubstates::PostGame)
+                    .with_system(freeze_physics_system),
             );
     }
 }
 
+// Events, then the gameplay systems, then the physics stages of Rapier
+fn fixed_step_schedule() -> Schedule {
+    let mut schedule = Schedule::default().with_run_criteria(fixed_timestep_criteria);
+    schedule
+        .add_stage(FixedStepStages::Events, SystemStage::parallel())
+        .add_stage(FixedStepStages::Gameplay, SystemStage::parallel());
+    for stage in [
+        PhysicsStages::SyncBackend,
+        PhysicsStages::StepSimulation,
+        PhysicsStages::Writeback,
+    ] {
+        schedule.add_stage(
+            stage.clone(),
+            SystemStage::parallel().with_system_set(PhysicsPlugin::get_systems(stage)),
+        );
+    }
+    schedule
+}
+
+// Runs a fixed step for every FIXED_TIMESTEP that has passed, the time left over carries on to the next frame
+// A step that changes the gameplay substate is the last of its frame, so the change is applied before the next one
+fn fixed_timestep_criteria(
+    time: Res<Time>,
+    gameplay_substate: Res<State<GameplayStateSubstates>>,
+    mut accumulator: Local<f32>,
+    mut stepping: Local<bool>,
+) -> ShouldRun {
+    if !*stepping {
+        *accumulator =
+            (*accumulator + time.delta_seconds()).min(FIXED_TIMESTEP * MAX_FIXED_STEPS_PER_FRAME);
+    } else if gameplay_substate.is_changed() {
+        *stepping = false;
+        return ShouldRun::No;
+    }
+
+    if *accumulator >= FIXED_TIMESTEP {
+        *accumulator -= FIXED_TIMESTEP;
+        *stepping = true;
+        ShouldRun::YesAndCheckAgain
+    } else {
+        *stepping = false;
+        ShouldRun::No
+    }
+}
+
+// Fixed step systems only run while the run is being played, not while it is set up, paused or over
+fn during_game_criteria(gameplay_substate: Res<State<GameplayStateSubstates>>) -> ShouldRun {
+    if *gameplay_substate.current() == GameplayStateSubstates::DuringGame {
+        ShouldRun::Yes
+    } else {
+        ShouldRun::No
+    }
+}
+
 // Initialize the physics system with the specified gravity value
+// The simulation stays stopped until the tower is built, so every run starts from the same state
 pub fn initilizate_physics_system(
     mut rapier_config: ResMut<RapierConfiguration>,
     gravity: Res<Gravity>,
 ) {
     rapier_config.gravity = Vec2::new(0.0, gravity.0);
+    rapier_config.timestep_mode = TimestepMode::Fixed {
+        dt: FIXED_TIMESTEP,
+        substeps: 1,
+    };
+    rapier_config.physics_pipeline_active = false;
+}
+
+fn reset_death_line_system(mut death_line: ResMut<DeathLine>) {
+    death_line.0 = DEATH_LINE;
+}
+
+fn start_physics_system(mut rapier_config: ResMut<RapierConfiguration>) {
+    rapier_config.physics_pipeline_active = true;
+}
+
+// Freeze the finished run behind the results screen
+fn freeze_physics_system(mut rapier_config: ResMut<RapierConfiguration>) {
+    rapier_config.physics_pipeline_active = false;
+}
+
+// Despawn everything left over from the previous run, new entities spawned this frame are not affected
+fn despawn_gameplay_entities_system(
+    mut commands: Commands,
+    gameplay_entity_query: Query<Entity, With<GameplayCleanup>>,
+) {
+    for entities in gameplay_entity_query.iter() {
+        commands.entity(entities).despawn_recursive();
+    }
 }
 
-// Switch to the "DuringGame" gameplay substate
-fn switch_gameplay_substates_system(mut gameplay_substate: ResMut<State<GameplayStateSubstates>>) {
+// Switch to the "DuringGame" gameplay substate once the tower has been spawned
+fn switch_gameplay_substates_system(
+    mut gameplay_substate: ResMut<State<GameplayStateSubstates>>,
+    platform_query: Query<(), With<Platform>>,
+) {
+    // The tower may still be waiting on its layout file
+    if platform_query.is_empty() {
+        return;
+    }
+
     gameplay_substate
         .set(GameplayStateSubstates::DuringGame)
         .unwrap();
 }
 
-// Reset the game when the top floor is reached or the death region is reached
+// Finish the run when the top floor is reached, and reset the game when the death region is reached
+// An endless run has no top floor, it finishes when the death region is reached instead
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
 fn game_completion_system(
-    mut player_query: Query<((&mut Player, &mut Transform), With<Player>)>,
-    mut platform_query: Query<&mut Platform, With<Platform>>,
+    mut commands: Commands,
+    mut player_query: Query<((&mut Player, &mut Transform, &mut Velocity), With<Player>)>,
+    mut platform_query: Query<
+        (
+            Entity,
+            &mut Platform,
+            &Transform,
+            Option<&mut CrumblingPlatform>,
+            Option<&Checkpoint>,
+        ),
+        (With<Platform>, Without<Player>),
+    >,
     mut ev_game_completed: EventReader<TopFloorReachedEvent>,
     mut ev_game_failed: EventReader<DeathRegionReachedEvent>,
+    mut ev_restart_requested: EventReader<RestartRequestedEvent>,
+    mut ev_player_respawned: EventWriter<PlayerRespawnedEvent>,
+    mut gameplay_substate: ResMut<State<GameplayStateSubstates>>,
+    mut lives: ResMut<Lives>,
+    game_mode: Res<GameMode>,
 ) {
     let (mut player_object, mut _player_transform) = player_query.single_mut();
 
-    // Helper function to reset the game
-    let mut reset_game = || {
-        player_object.1.translation = Vec3::new(0.0, -PLAYER_SIZE * 2.0, 0.0);
-        player_object.0.score = 0;
+    // Finish the run when the top floor is reached, the results are shown from "PostGame"
+    if ev_game_completed.iter().next().is_some() {
+        let _ = gameplay_substate.set(GameplayStateSubstates::PostGame);
+        return;
+    }
 
-        for mut platform_object in platform_query.iter_mut() {
-            if platform_object.already_collided {
-                platform_object.already_collided = false;
-            }
-        }
-    };
+    let restart = ev_restart_requested.iter().next().is_some();
+    let failed = ev_game_failed.iter().next().is_some();
+    if !restart && !failed {
+        return;
+    }
+
+    if *game_mode == GameMode::Endless {
+        // Restarting abandons the run, it is neither recorded nor shown, and the tower starts over
+        let next_substate = if restart {
+            GameplayStateSubstates::PreGame
+        } else {
+            GameplayStateSubstates::PostGame
+        };
+        let _ = gameplay_substate.set(next_substate);
+        return;
+    }
 
-    // Reset the game when the top floor is reached
-    for _ev in ev_game_completed.iter() {
-        reset_game();
+    // Falling with lives left comes back at the highest checkpoint reached, restarting or running
+    // out of lives resets the game
+    let checkpoint = platform_query
+        .iter()
+        .filter(|(_, platform_object, _, _, checkpoint)| {
+            checkpoint.is_some() && platform_object.already_collided
+        })
+        .map(|(_, _, platform_transform, _, _)| platform_transform.translation)
+        .max_by(|first, second| first.y.total_cmp(&second.y))
+        .filter(|_| !restart && lives.0 > 0);
+    match checkpoint {
+        Some(checkpoint) => {
+            lives.0 -= 1;
+            player_object.1.translation =
+                Vec3::new(checkpoint.x, checkpoint.y + CHECKPOINT_RESPAWN_HEIGHT, 0.0);
+            // Starts from a standstill instead of at the speed of the fall
+            player_object.2.linvel = Vec2::ZERO;
+        }
+        None => {
+            lives.0 = STARTING_LIVES;
+            player_object.1.translation = Vec3::new(0.0, -PLAYER_SIZE * 2.0, 0.0);
+        }
     }
+    player_object.0.surface = Surface::Normal;
+    ev_player_respawned.send(PlayerRespawnedEvent(player_object.1.translation));
 
-    // Reset the game when the death region is reached
-    for _ev in ev_game_failed.iter() {
-        reset_game();
+    let mut score = 0;
+    for (platform_entity, mut platform_object, platform_transform, crumbling_platform, _) in
+        platform_query.iter_mut()
+    {
+        // The floors up to the checkpoint still count towards the score
+        let above_checkpoint =
+            checkpoint.is_none_or(|checkpoint| platform_transform.translation.y > checkpoint.y);
+        if above_checkpoint {
+            platform_object.already_collided = false;
+        }
+        if platform_object.already_collided {
+            score += 1;
+        }
+        // Crumbled platforms come back for the next attempt
+        if let Some(mut crumbling_platform) = crumbling_platform {
+            crumbling_platform.restore(&mut commands, platform_entity);
+        }
     }
+    player_object.0.score = score;
 }
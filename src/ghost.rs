@@ -0,0 +1,107 @@
+use crate::difficulty::Difficulty;
+use crate::gameplay_state::FixedStepAppExt;
+use crate::platforms::LevelSeed;
+use crate::player_input::InputSource;
+use crate::replay::{Replay, ReplayFrame};
+use crate::settings::GameSettings;
+use crate::{GameMode, GameplayCleanup, GameplayStateSubstates, PLAYER_SIZE};
+use bevy::prelude::*;
+
+pub struct GhostPlugin;
+
+// Follows the recorded positions of the fastest run on the current tower
+#[derive(Component)]
+struct Ghost {
+    frames: Vec<ReplayFrame>,
+    frame: usize,
+}
+
+const GHOST_ALPHA: f32 = 0.35;
+
+impl Plugin for GhostPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(
+            SystemSet::on_enter(GameplayStateSubstates::PreGame).with_system(spawn_ghost_system),
+        )
+        .add_fixed_step_system_set(SystemSet::new().with_system(move_ghost_system))
+        .add_system(ghost_visibility_system);
+    }
+}
+
+fn spawn_ghost_system(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    settings: Res<GameSettings>,
+    input_source: Res<InputSource>,
+    level_seed: Res<LevelSeed>,
+    game_mode: Res<GameMode>,
+    difficulty: Res<Difficulty>,
+) {
+    // A played back run is already a recording, racing it against another one would only get in the way
+    if *input_source == InputSource::Replay {
+        return;
+    }
+    let Some(replay) = Replay::load_best(*level_seed, *game_mode, *difficulty) else {
+        return;
+    };
+    let Some(first_frame) = replay.frames.first() else {
+        return;
+    };
+
+    commands.spawn((
+        SpriteBundle {
+            sprite: Sprite {
+                color: Color::rgba(1.0, 1.0, 1.0, GHOST_ALPHA),
+                custom_size: Some(Vec2::new(PLAYER_SIZE, PLAYER_SIZE)),
+                ..Default::default()
+            },
+            texture: asset_server.load("PlayerTexture.png"),
+            // Drawn behind the player and the platforms
+            transform: Transform::from_xyz(first_frame.position.0, first_frame.position.1, -0.5),
+            visibility: Visibility {
+                is_visible: settings.ghost_enabled,
+            },
+            ..Default::default()
+        },
+        Ghost {
+            frames: replay.frames,
+            frame: 0,
+        },
+        GameplayCleanup,
+    ));
+}
+
+// Moves the ghost one recorded frame forward every fixed step, it stays where the recording ended once it runs out
+fn move_ghost_system(mut ghost_query: Query<(&mut Ghost, &mut Transform, &mut Sprite)>) {
+    for (mut ghost, mut ghost_transform, mut ghost_sprite) in ghost_query.iter_mut() {
+        let Some(frame) = ghost.frames.get(ghost.frame).copied() else {
+            continue;
+        };
+        ghost.frame += 1;
+
+        ghost_transform.translation.x = frame.position.0;
+        ghost_transform.translation.y = frame.position.1;
+
+        // Face the same way the player did, like `player_input_system` does
+        if frame.input.x_axis > 0.0 {
+            ghost_sprite.flip_x = false;
+        }
+        if frame.input.x_axis < 0.0 {
+            ghost_sprite.flip_x = true;
+        }
+    }
+}
+
+// The ghost can be turned on and off from the settings menu in the middle of a run
+fn ghost_visibility_system(
+    mut ghost_query: Query<&mut Visibility, With<Ghost>>,
+    settings: Res<GameSettings>,
+) {
+    if !settings.is_changed() {
+        return;
+    }
+
+    for mut visibility in ghost_query.iter_mut() {
+        visibility.is_visible = settings.ghost_enabled;
+    }
+}
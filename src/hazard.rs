@@ -0,0 +1,170 @@
+use crate::difficulty::Difficulty;
+use crate::gameplay_state::{FixedStepAppExt, PlayerRespawnedEvent, FIXED_TIMESTEP};
+use crate::player::PLAYER_RADIUS;
+use crate::settings::GameSettings;
+use crate::{AppState, DeathRegionReachedEvent, GameplayCleanup, GameplayStateSubstates};
+use crate::{Player, WindowDimensions, PLAYER_SIZE};
+use bevy::prelude::*;
+use bevy::sprite::Anchor;
+
+pub struct HazardPlugin;
+
+// How far below the player the hazard starts, and is pushed back to when they respawn, in window heights
+const HAZARD_GAP: f32 = 0.55;
+// Past the bloom threshold of the camera, so the hazard glows
+const HAZARD_COLOR: Color = Color::rgb(3.2, 0.9, 0.2);
+// Drawn over the platforms it has swallowed
+const HAZARD_Z: f32 = 0.5;
+// The surface of the lava is tiled along the top edge and drifts sideways, in pixels per second
+const LAVA_TILE_SIZE: Vec2 = Vec2::new(64.0, 32.0);
+const LAVA_DRIFT_SPEED: f32 = 18.0;
+
+// Whether the current run has the rising hazard, picked in the settings menu and fixed for the length of a run
+#[derive(Resource, Default)]
+pub struct HazardEnabled(pub bool);
+
+// Lava rising up from under the tower, its transform is the top edge
+#[derive(Component)]
+struct RisingHazard {
+    // Seconds since it started, or since the player last respawned
+    elapsed: f32,
+}
+
+// A tile of the lava's surface, it wraps back around once it has drifted off the left of the hazard
+#[derive(Component)]
+struct LavaSurface {
+    left: f32,
+    span: f32,
+}
+
+impl Plugin for HazardPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<HazardEnabled>()
+            .add_system_set(
+                SystemSet::on_enter(AppState::Gameplay).with_system(apply_hazard_setting_system),
+            )
+            .add_system_set(
+                SystemSet::on_update(GameplayStateSubstates::PreGame)
+                    .with_system(spawn_hazard_system),
+            )
+            .add_fixed_step_system_set(SystemSet::new().with_system(rising_hazard_system))
+            .add_system_set(
+                SystemSet::on_update(GameplayStateSubstates::DuringGame)
+                    .with_system(lava_surface_system),
+            );
+    }
+}
+
+fn apply_hazard_setting_system(
+    mut hazard_enabled: ResMut<HazardEnabled>,
+    settings: Res<GameSettings>,
+) {
+    hazard_enabled.0 = settings.hazard_enabled;
+}
+
+// Spawned once the run is set up, so a played back replay has already decided whether it has one
+fn spawn_hazard_system(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    window: Res<WindowDimensions>,
+    hazard_enabled: Res<HazardEnabled>,
+    hazard_query: Query<(), With<RisingHazard>>,
+) {
+    if !hazard_enabled.0 || !hazard_query.is_empty() {
+        return;
+    }
+
+    let top = -PLAYER_SIZE * 2.0 - window.height * HAZARD_GAP;
+    // Wide enough to cover the window at any zoom, and deep enough that its bottom is never seen
+    let size = Vec2::new(window.width * 1.5, window.height * 2.0);
+    let lava_texture = asset_server.load("LavaTexture.png");
+    let tile_count = (size.x / LAVA_TILE_SIZE.x).ceil() as usize + 1;
+    let left = -size.x / 2.0;
+
+    commands
+        .spawn((
+            SpatialBundle::from_transform(Transform::from_xyz(0.0, top, HAZARD_Z)),
+            RisingHazard { elapsed: 0.0 },
+            GameplayCleanup,
+        ))
+        .with_children(|parent| {
+            // The body starts under the waves of the surface, the lower half of the tiles covers the seam
+            parent.spawn(SpriteBundle {
+                sprite: Sprite {
+                    color: HAZARD_COLOR,
+                    custom_size: Some(size),
+                    anchor: Anchor::TopCenter,
+                    ..default()
+                },
+                transform: Transform::from_xyz(0.0, -LAVA_TILE_SIZE.y / 2.0, 0.0),
+                ..default()
+            });
+
+            // The texture is tinted with the same colour, so the surface glows like the rest of it
+            for index in 0..tile_count {
+                parent.spawn((
+                    SpriteBundle {
+                        sprite: Sprite {
+                            color: HAZARD_COLOR,
+                            custom_size: Some(LAVA_TILE_SIZE),
+                            anchor: Anchor::TopLeft,
+                            ..default()
+                        },
+                        texture: lava_texture.clone(),
+                        transform: Transform::from_xyz(
+                            left + index as f32 * LAVA_TILE_SIZE.x,
+                            0.0,
+                            0.01,
+                        ),
+                        ..default()
+                    },
+                    LavaSurface {
+                        left,
+                        span: tile_count as f32 * LAVA_TILE_SIZE.x,
+                    },
+                ));
+            }
+        });
+}
+
+// Drifts the surface tiles along the top edge, only for show so it goes by the frame time
+fn lava_surface_system(
+    mut lava_surface_query: Query<(&LavaSurface, &mut Transform)>,
+    time: Res<Time>,
+) {
+    for (lava_surface, mut transform) in lava_surface_query.iter_mut() {
+        transform.translation.x -= LAVA_DRIFT_SPEED * time.delta_seconds();
+        if transform.translation.x + LAVA_TILE_SIZE.x < lava_surface.left {
+            transform.translation.x += lava_surface.span;
+        }
+    }
+}
+
+// Raises the hazard along the difficulty's speed curve and fails the attempt once it touches the player
+fn rising_hazard_system(
+    mut hazard_query: Query<(&mut RisingHazard, &mut Transform), Without<Player>>,
+    player_query: Query<&Transform, With<Player>>,
+    mut respawn_events: EventReader<PlayerRespawnedEvent>,
+    mut failure_event: EventWriter<DeathRegionReachedEvent>,
+    window: Res<WindowDimensions>,
+    difficulty: Res<Difficulty>,
+) {
+    let Ok((mut hazard, mut hazard_transform)) = hazard_query.get_single_mut() else {
+        return;
+    };
+
+    // A respawned player gets the same head start as at the beginning of the run
+    if let Some(respawn) = respawn_events.iter().last() {
+        hazard.elapsed = 0.0;
+        hazard_transform.translation.y = respawn.0.y - window.height * HAZARD_GAP;
+    }
+
+    hazard.elapsed += FIXED_TIMESTEP;
+    hazard_transform.translation.y +=
+        difficulty.profile().hazard.speed_at(hazard.elapsed) * FIXED_TIMESTEP;
+
+    let player_bottom = player_query.single().translation.y - PLAYER_RADIUS;
+    if player_bottom < hazard_transform.translation.y {
+        failure_event.send(DeathRegionReachedEvent);
+    }
+}
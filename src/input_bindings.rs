@@ -0,0 +1,249 @@
+use crate::save_data::{data_directory, write_file_atomically};
+use bevy::input::InputSystem;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::PathBuf;
+
+pub struct InputBindingsPlugin;
+
+const BINDINGS_FILE_NAME: &str = "bindings.ron";
+// Written into the bindings file, bump it when the format changes in a way older files need converting
+const BINDINGS_VERSION: u32 = 1;
+
+// Something the player can do, gameplay code reads these from `Input<InputAction>` instead of raw keys
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum InputAction {
+    MoveLeft,
+    MoveRight,
+    // Only used when jumping is set to the jump button
+    Jump,
+    FastFall,
+    // Drops down through the one-way platform the player is standing on
+    DropThrough,
+    Restart,
+    Pause,
+}
+
+// Which keys and gamepad buttons trigger each action, loaded from the bindings file
+#[derive(Resource, Serialize, Deserialize, Debug, Clone)]
+pub struct InputBindings {
+    version: u32,
+    #[serde(default)]
+    keys: BTreeMap<InputAction, Vec<KeyCode>>,
+    #[serde(default)]
+    gamepad_buttons: BTreeMap<InputAction, Vec<GamepadButtonType>>,
+}
+
+impl Plugin for InputBindingsPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(InputBindings::load())
+            .init_resource::<Input<InputAction>>()
+            .add_system_to_stage(
+                CoreStage::PreUpdate,
+                update_action_input_system.after(InputSystem),
+            );
+    }
+}
+
+impl InputAction {
+    pub const ALL: [InputAction; 7] = [
+        InputAction::MoveLeft,
+        InputAction::MoveRight,
+        InputAction::Jump,
+        InputAction::FastFall,
+        InputAction::DropThrough,
+        InputAction::Restart,
+        InputAction::Pause,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            InputAction::MoveLeft => "MOVE LEFT",
+            InputAction::MoveRight => "MOVE RIGHT",
+            InputAction::Jump => "JUMP",
+            InputAction::FastFall => "FAST FALL",
+            InputAction::DropThrough => "DROP THROUGH",
+            InputAction::Restart => "RESTART",
+            InputAction::Pause => "PAUSE",
+        }
+    }
+
+    fn default_keys(&self) -> Vec<KeyCode> {
+        match self {
+            InputAction::MoveLeft => vec![KeyCode::A, KeyCode::Left],
+            InputAction::MoveRight => vec![KeyCode::D, KeyCode::Right],
+            InputAction::Jump => vec![KeyCode::Space, KeyCode::W],
+            InputAction::FastFall => vec![KeyCode::S],
+            InputAction::DropThrough => vec![KeyCode::Down],
+            InputAction::Restart => vec![KeyCode::R],
+            InputAction::Pause => vec![KeyCode::Escape, KeyCode::P],
+        }
+    }
+
+    fn default_gamepad_buttons(&self) -> Vec<GamepadButtonType> {
+        match self {
+            InputAction::MoveLeft => vec![GamepadButtonType::DPadLeft],
+            InputAction::MoveRight => vec![GamepadButtonType::DPadRight],
+            InputAction::Jump => vec![GamepadButtonType::South],
+            InputAction::FastFall => vec![GamepadButtonType::East],
+            InputAction::DropThrough => vec![GamepadButtonType::DPadDown],
+            InputAction::Restart => vec![GamepadButtonType::Select],
+            InputAction::Pause => vec![GamepadButtonType::Start],
+        }
+    }
+}
+
+impl Default for InputBindings {
+    fn default() -> Self {
+        let mut bindings = InputBindings {
+            version: BINDINGS_VERSION,
+            keys: BTreeMap::new(),
+            gamepad_buttons: BTreeMap::new(),
+        };
+        bindings.fill_missing_actions();
+        bindings
+    }
+}
+
+impl InputBindings {
+    fn path() -> Option<PathBuf> {
+        data_directory().map(|directory| directory.join(BINDINGS_FILE_NAME))
+    }
+
+    // A missing or unreadable file falls back to the default bindings, it is only written once they are changed
+    fn load() -> Self {
+        let Some(path) = Self::path() else {
+            return InputBindings::default();
+        };
+
+        let contents = match fs::read_to_string(&path) {
+            Ok(contents) => contents,
+            Err(err) if err.kind() == ErrorKind::NotFound => return InputBindings::default(),
+            Err(err) => {
+                error!("Could not read bindings file {}: {}", path.display(), err);
+                return InputBindings::default();
+            }
+        };
+
+        let mut bindings = match ron::from_str::<InputBindings>(&contents) {
+            Ok(bindings) => bindings,
+            Err(err) => {
+                error!("Bindings file {} is invalid: {}", path.display(), err);
+                return InputBindings::default();
+            }
+        };
+        bindings.fill_missing_actions();
+
+        for action in InputAction::ALL {
+            for key in bindings.keys(action) {
+                // Each conflict is reported once, from the action that comes first
+                if let Some(other_action) = bindings
+                    .key_conflict(action, *key)
+                    .filter(|other_action| *other_action > action)
+                {
+                    warn!(
+                        "{:?} is bound to both {:?} and {:?}",
+                        key, action, other_action
+                    );
+                }
+            }
+        }
+
+        bindings
+    }
+
+    pub fn save(&self) {
+        let Some(path) = Self::path() else {
+            return;
+        };
+
+        let contents = match ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default()) {
+            Ok(contents) => contents,
+            Err(err) => {
+                error!("Could not serialize bindings: {}", err);
+                return;
+            }
+        };
+
+        if let Err(err) = write_file_atomically(&path, &contents) {
+            error!("Could not write bindings file {}: {}", path.display(), err);
+        }
+    }
+
+    // Actions left out of the bindings file keep their default bindings
+    fn fill_missing_actions(&mut self) {
+        for action in InputAction::ALL {
+            self.keys
+                .entry(action)
+                .or_insert_with(|| action.default_keys());
+            self.gamepad_buttons
+                .entry(action)
+                .or_insert_with(|| action.default_gamepad_buttons());
+        }
+    }
+
+    pub fn keys(&self, action: InputAction) -> &[KeyCode] {
+        self.keys.get(&action).map_or(&[], |keys| keys.as_slice())
+    }
+
+    // The other action a key already triggers, if any
+    pub fn key_conflict(&self, action: InputAction, key: KeyCode) -> Option<InputAction> {
+        InputAction::ALL
+            .into_iter()
+            .find(|other_action| *other_action != action && self.keys(*other_action).contains(&key))
+    }
+
+    // Makes the key the action's main binding, the previous main binding is kept as the alternative
+    pub fn rebind_key(&mut self, action: InputAction, key: KeyCode) {
+        let keys = self.keys.entry(action).or_default();
+        keys.retain(|bound_key| *bound_key != key);
+        keys.insert(0, key);
+        keys.truncate(2);
+    }
+
+    pub fn reset_to_defaults(&mut self) {
+        *self = InputBindings::default();
+    }
+
+    fn pressed(
+        &self,
+        action: InputAction,
+        keyboard_input: &Input<KeyCode>,
+        gamepads: &Gamepads,
+        gamepad_buttons: &Input<GamepadButton>,
+    ) -> bool {
+        let bound_buttons = self
+            .gamepad_buttons
+            .get(&action)
+            .map_or(&[][..], |buttons| buttons.as_slice());
+
+        keyboard_input.any_pressed(self.keys(action).iter().copied())
+            || gamepads.iter().any(|gamepad| {
+                bound_buttons
+                    .iter()
+                    .any(|button| gamepad_buttons.pressed(GamepadButton::new(gamepad, *button)))
+            })
+    }
+}
+
+// Turns the raw keyboard and gamepad state into action state, before any other system reads it
+fn update_action_input_system(
+    mut action_input: ResMut<Input<InputAction>>,
+    input_bindings: Res<InputBindings>,
+    keyboard_input: Res<Input<KeyCode>>,
+    gamepads: Res<Gamepads>,
+    gamepad_buttons: Res<Input<GamepadButton>>,
+) {
+    action_input.clear();
+
+    for action in InputAction::ALL {
+        if input_bindings.pressed(action, &keyboard_input, &gamepads, &gamepad_buttons) {
+            action_input.press(action);
+        } else {
+            action_input.release(action);
+        }
+    }
+}
@@ -1,20 +1,48 @@
+use app_state::{AppState, AppStatePlugin};
 use audio_manager::AudioManagerPlugin;
 use bevy::{prelude::*, window::PresentMode};
 use gameplay_state::{
-    DeathRegionReachedEvent, GameplayStatePlugin, GameplayStateSubstates, TopFloorReachedEvent,
+    DeathRegionReachedEvent, GameMode, GameplayCleanup, GameplayStatePlugin,
+    GameplayStateSubstates, TopFloorReachedEvent,
 };
+use input_bindings::InputBindingsPlugin;
 use platforms::Platform;
 use player::{Player, PLAYER_SIZE};
+use save_data::SaveDataPlugin;
+use settings::SettingsPlugin;
 use window_manager::{GameWindowPlugin, WindowDimensions};
 // use bevy_inspector_egui::WorldInspectorPlugin;
 
+mod app_state;
 mod audio_manager;
+mod checkpoints;
+mod coins;
+mod controls_menu;
+mod daily;
+mod difficulty;
+mod endless;
 mod game_camera;
 mod game_timer;
 mod gameplay_state;
+mod ghost;
+mod hazard;
+mod input_bindings;
+mod main_menu;
+mod one_way_platforms;
+mod pause_menu;
 mod platform_indicators;
+mod platform_path;
 mod platforms;
 mod player;
+mod player_animation;
+mod player_input;
+mod reachability;
+mod replay;
+mod results_screen;
+mod save_data;
+mod scoring;
+mod settings;
+mod tower_layout;
 mod ui;
 mod window_manager;
 
@@ -41,8 +69,29 @@ fn main() {
                 .set(ImagePlugin::default_nearest()),
         )
         .add_plugin(GameWindowPlugin)
+        .add_plugin(SettingsPlugin)
+        .add_plugin(InputBindingsPlugin)
+        .add_plugin(SaveDataPlugin)
+        .add_plugin(AppStatePlugin)
         .add_plugin(GameplayStatePlugin)
         .add_plugin(AudioManagerPlugin)
         // .add_plugin(WorldInspectorPlugin::new())
         .run();
 }
+
+// Returns the value passed to a command line flag, accepting both `--flag value` and `--flag=value`
+pub fn command_line_value(flag: &str) -> Option<String> {
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        if let Some(value) = arg
+            .strip_prefix(flag)
+            .and_then(|rest| rest.strip_prefix('='))
+        {
+            return Some(value.to_string());
+        }
+        if arg == flag {
+            return args.next();
+        }
+    }
+    None
+}
@@ -0,0 +1,256 @@
+use crate::daily::{DailyChallenge, DailyDate};
+use crate::platforms::LevelSeed;
+use crate::save_data::SaveData;
+use crate::settings::{GameSettings, SettingsMenuState};
+use crate::tower_layout::TowerLayoutHandle;
+use crate::ui::{
+    spawn_menu_button, spawn_menu_root, spawn_menu_row, spawn_menu_text, spawn_menu_title,
+};
+use crate::{AppState, GameMode};
+use bevy::app::AppExit;
+use bevy::prelude::*;
+
+pub struct MainMenuPlugin;
+
+#[derive(Component)]
+struct MainMenu;
+
+#[derive(Component)]
+struct LeaderboardMenu;
+
+#[derive(Component, Clone, Copy)]
+enum MainMenuButton {
+    Play,
+    Endless,
+    Daily,
+    Settings,
+    Leaderboard,
+    Quit,
+}
+
+#[derive(Component, Clone, Copy)]
+enum LeaderboardButton {
+    Back,
+}
+
+// The number of runs listed per mode, the full leaderboard is kept in the save file
+const LEADERBOARD_ROWS: usize = 5;
+
+impl Plugin for MainMenuPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(
+            SystemSet::on_enter(AppState::MainMenu).with_system(spawn_main_menu_system),
+        )
+        .add_system_set(
+            SystemSet::on_update(AppState::MainMenu).with_system(main_menu_button_system),
+        )
+        .add_system_set(
+            SystemSet::on_exit(AppState::MainMenu).with_system(despawn_main_menu_system),
+        )
+        .add_system_set(
+            SystemSet::on_enter(AppState::Leaderboard).with_system(spawn_leaderboard_system),
+        )
+        .add_system_set(
+            SystemSet::on_update(AppState::Leaderboard).with_system(leaderboard_button_system),
+        )
+        .add_system_set(
+            SystemSet::on_exit(AppState::Leaderboard).with_system(despawn_leaderboard_system),
+        )
+        // The main menu steps aside while the settings menu is open
+        .add_system_set(
+            SystemSet::on_enter(SettingsMenuState::Open).with_system(despawn_main_menu_system),
+        )
+        .add_system_set(
+            SystemSet::on_exit(SettingsMenuState::Open).with_system(respawn_main_menu_system),
+        );
+    }
+}
+
+fn spawn_main_menu_system(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    save_data: Res<SaveData>,
+) {
+    spawn_main_menu(&mut commands, &asset_server, &save_data);
+}
+
+fn respawn_main_menu_system(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    save_data: Res<SaveData>,
+    app_state: Res<State<AppState>>,
+) {
+    // Only bring the main menu back if the settings menu was opened from it
+    if *app_state.current() == AppState::MainMenu {
+        spawn_main_menu(&mut commands, &asset_server, &save_data);
+    }
+}
+
+fn spawn_main_menu(commands: &mut Commands, asset_server: &AssetServer, save_data: &SaveData) {
+    let font = asset_server.load("papercut.ttf");
+
+    // Once the day's scored run has been started, the daily challenge can only be practiced
+    let daily_label = if save_data.daily_attempted(DailyDate::today()) {
+        "DAILY PRACTICE"
+    } else {
+        "DAILY CHALLENGE"
+    };
+
+    let root = spawn_menu_root(commands, MainMenu);
+    commands.entity(root).with_children(|parent| {
+        spawn_menu_title(parent, &font, "FLOOR FIFTY");
+        spawn_menu_button(parent, &font, "PLAY", MainMenuButton::Play);
+        // The other modes share a row so the menu still fits the screen
+        spawn_menu_row(parent).with_children(|row| {
+            spawn_menu_button(row, &font, "ENDLESS", MainMenuButton::Endless);
+            spawn_menu_button(row, &font, daily_label, MainMenuButton::Daily);
+        });
+        spawn_menu_button(parent, &font, "SETTINGS", MainMenuButton::Settings);
+        spawn_menu_button(parent, &font, "LEADERBOARD", MainMenuButton::Leaderboard);
+        spawn_menu_button(parent, &font, "QUIT", MainMenuButton::Quit);
+    });
+}
+
+#[allow(clippy::too_many_arguments)]
+fn main_menu_button_system(
+    button_query: Query<(&Interaction, &MainMenuButton), Changed<Interaction>>,
+    mut app_state: ResMut<State<AppState>>,
+    mut settings_menu_state: ResMut<State<SettingsMenuState>>,
+    mut level_seed: ResMut<LevelSeed>,
+    mut game_mode: ResMut<GameMode>,
+    mut daily_challenge: ResMut<DailyChallenge>,
+    tower_layout_handle: Res<TowerLayoutHandle>,
+    mut exit_event: EventWriter<AppExit>,
+) {
+    for (interaction, button) in button_query.iter() {
+        if *interaction != Interaction::Clicked {
+            continue;
+        }
+
+        match button {
+            MainMenuButton::Play => {
+                // Every new run gets a fresh tower unless a seed was given on the command line
+                *level_seed = LevelSeed::from_env().unwrap_or_else(LevelSeed::random);
+                *game_mode = if tower_layout_handle.0.is_some() {
+                    GameMode::CustomTower
+                } else {
+                    GameMode::Standard
+                };
+                let _ = app_state.set(AppState::Gameplay);
+            }
+            MainMenuButton::Endless => {
+                *level_seed = LevelSeed::from_env().unwrap_or_else(LevelSeed::random);
+                *game_mode = GameMode::Endless;
+                let _ = app_state.set(AppState::Gameplay);
+            }
+            // The date is read again every time, the game may have been left open past midnight
+            MainMenuButton::Daily => {
+                *daily_challenge = DailyChallenge::for_date(DailyDate::today());
+                *level_seed = daily_challenge.seed;
+                *game_mode = GameMode::Daily;
+                let _ = app_state.set(AppState::Gameplay);
+            }
+            MainMenuButton::Settings => {
+                let _ = settings_menu_state.set(SettingsMenuState::Open);
+            }
+            MainMenuButton::Leaderboard => {
+                let _ = app_state.set(AppState::Leaderboard);
+            }
+            MainMenuButton::Quit => {
+                exit_event.send(AppExit);
+            }
+        }
+    }
+}
+
+fn despawn_main_menu_system(mut commands: Commands, menu_query: Query<Entity, With<MainMenu>>) {
+    for entities in menu_query.iter() {
+        commands.entity(entities).despawn_recursive();
+    }
+}
+
+fn spawn_leaderboard_system(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    save_data: Res<SaveData>,
+    settings: Res<GameSettings>,
+) {
+    let font = asset_server.load("papercut.ttf");
+
+    let root = spawn_menu_root(&mut commands, LeaderboardMenu);
+    commands.entity(root).with_children(|parent| {
+        spawn_menu_title(parent, &font, "LEADERBOARD");
+
+        // Generated towers are listed for the difficulty picked in the settings menu
+        let standard_heading = format!("STANDARD {}", settings.difficulty.label());
+        let endless_heading = format!("ENDLESS {}", settings.difficulty.label());
+        let mut any_runs = false;
+        for (mode, heading) in [
+            (GameMode::Standard, standard_heading.as_str()),
+            (GameMode::Endless, endless_heading.as_str()),
+            (GameMode::Daily, "DAILY CHALLENGE"),
+            (GameMode::CustomTower, "CUSTOM TOWER"),
+        ] {
+            let records = save_data.leaderboard((mode, settings.difficulty.of_run(mode)));
+            if records.is_empty() {
+                continue;
+            }
+            any_runs = true;
+
+            spawn_menu_text(parent, &font, heading);
+            for (place, record) in records.iter().take(LEADERBOARD_ROWS).enumerate() {
+                // The seed of an outdated run builds a different tower now, so it is not worth retrying
+                let seed = match record.seed {
+                    Some(seed) if record.seed_outdated() => format!("   OLD SEED {}", seed),
+                    Some(seed) => format!("   SEED {}", seed),
+                    None => String::new(),
+                };
+                spawn_menu_text(
+                    parent,
+                    &font,
+                    &format!(
+                        "{}.  {:.2}   {} FLOORS{}",
+                        place + 1,
+                        record.time,
+                        record.score,
+                        seed
+                    ),
+                );
+            }
+        }
+
+        if !any_runs {
+            spawn_menu_text(parent, &font, "NO RUNS RECORDED YET");
+        }
+        spawn_menu_button(parent, &font, "BACK", LeaderboardButton::Back);
+    });
+}
+
+fn leaderboard_button_system(
+    button_query: Query<(&Interaction, &LeaderboardButton), Changed<Interaction>>,
+    mut keyboard_input: ResMut<Input<KeyCode>>,
+    mut app_state: ResMut<State<AppState>>,
+) {
+    let mut back = keyboard_input.clear_just_pressed(KeyCode::Escape);
+
+    for (interaction, button) in button_query.iter() {
+        if *interaction == Interaction::Clicked {
+            match button {
+                LeaderboardButton::Back => back = true,
+            }
+        }
+    }
+
+    if back {
+        let _ = app_state.set(AppState::MainMenu);
+    }
+}
+
+fn despawn_leaderboard_system(
+    mut commands: Commands,
+    menu_query: Query<Entity, With<LeaderboardMenu>>,
+) {
+    for entities in menu_query.iter() {
+        commands.entity(entities).despawn_recursive();
+    }
+}
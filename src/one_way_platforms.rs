@@ -0,0 +1,55 @@
+use crate::{Platform, Player};
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+use bevy_rapier2d::rapier::math::Vector;
+
+// How far a contact may lean away from straight up and still hold the player, in radians
+const ALLOWED_CONTACT_ANGLE: f32 = 0.5;
+
+// The components the hook looks at, every collider is either a platform, the player or neither
+pub type OneWayPlatformHookData = (Option<&'static Platform>, Option<&'static Player>);
+
+// Rapier's contact modification hook, lets the player through one-way platforms from below and
+// through the platform they are dropping through
+pub struct OneWayPlatformHooks;
+
+fn is_one_way(hook_data: &Query<OneWayPlatformHookData>, collider: Entity) -> bool {
+    hook_data
+        .get(collider)
+        .is_ok_and(|(platform, _)| platform.is_some_and(|platform| platform.one_way))
+}
+
+impl PhysicsHooksWithQuery<OneWayPlatformHookData> for OneWayPlatformHooks {
+    fn modify_solver_contacts(
+        &self,
+        context: ContactModificationContextView,
+        hook_data: &Query<OneWayPlatformHookData>,
+    ) {
+        let (collider1, collider2) = (context.collider1(), context.collider2());
+        let (platform, other, platform_first) = if is_one_way(hook_data, collider1) {
+            (collider1, collider2, true)
+        } else if is_one_way(hook_data, collider2) {
+            (collider2, collider1, false)
+        } else {
+            return;
+        };
+
+        if hook_data.get(other).is_ok_and(|(_, player)| {
+            player.is_some_and(|player| player.dropping_through() == Some(platform))
+        }) {
+            context.raw.solver_contacts.clear();
+            return;
+        }
+
+        // The contact normal points from the first collider to the second, only contacts pushing up
+        // out of the top of the platform are kept
+        let allowed_normal = if platform_first {
+            Vector::y()
+        } else {
+            -Vector::y()
+        };
+        context
+            .raw
+            .update_as_oneway_platform(&allowed_normal, ALLOWED_CONTACT_ANGLE);
+    }
+}
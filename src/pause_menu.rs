@@ -0,0 +1,157 @@
+use crate::input_bindings::InputAction;
+use crate::player_input::RestartRequested;
+use crate::settings::SettingsMenuState;
+use crate::ui::{spawn_menu_button, spawn_menu_root, spawn_menu_title};
+use crate::{AppState, GameplayStateSubstates};
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+
+pub struct PauseMenuPlugin;
+
+#[derive(Component)]
+struct PauseMenu;
+
+#[derive(Component, Clone, Copy)]
+enum PauseMenuButton {
+    Resume,
+    Restart,
+    Settings,
+    MainMenu,
+}
+
+impl Plugin for PauseMenuPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(
+            SystemSet::on_update(GameplayStateSubstates::DuringGame)
+                .with_system(pause_input_system),
+        )
+        .add_system_set(
+            SystemSet::on_enter(GameplayStateSubstates::Paused)
+                .with_system(pause_physics_system)
+                .with_system(spawn_pause_menu_system),
+        )
+        .add_system_set(
+            SystemSet::on_update(GameplayStateSubstates::Paused)
+                .with_system(resume_input_system)
+                .with_system(pause_menu_button_system),
+        )
+        .add_system_set(
+            SystemSet::on_exit(GameplayStateSubstates::Paused)
+                .with_system(resume_physics_system)
+                .with_system(despawn_pause_menu_system),
+        )
+        // The pause menu steps aside while the settings menu is open
+        .add_system_set(
+            SystemSet::on_enter(SettingsMenuState::Open).with_system(despawn_pause_menu_system),
+        )
+        .add_system_set(
+            SystemSet::on_exit(SettingsMenuState::Open).with_system(respawn_pause_menu_system),
+        );
+    }
+}
+
+// Pauses the run when a pause binding is pressed
+fn pause_input_system(
+    mut action_input: ResMut<Input<InputAction>>,
+    mut gameplay_substate: ResMut<State<GameplayStateSubstates>>,
+) {
+    // Consume the press so the pause menu does not resume on the same frame
+    if action_input.clear_just_pressed(InputAction::Pause) {
+        // Pushing keeps "DuringGame" on the stack, so its systems are simply suspended
+        let _ = gameplay_substate.push(GameplayStateSubstates::Paused);
+    }
+}
+
+// Resumes the run when a pause binding is pressed again
+fn resume_input_system(
+    mut action_input: ResMut<Input<InputAction>>,
+    mut gameplay_substate: ResMut<State<GameplayStateSubstates>>,
+    settings_menu_state: Res<State<SettingsMenuState>>,
+) {
+    // Escape belongs to the settings menu while it is open
+    if *settings_menu_state.current() != SettingsMenuState::Closed {
+        return;
+    }
+
+    if action_input.clear_just_pressed(InputAction::Pause) {
+        let _ = gameplay_substate.pop();
+    }
+}
+
+// Stops Rapier from stepping so bodies keep their positions and velocities while paused
+fn pause_physics_system(mut rapier_config: ResMut<RapierConfiguration>) {
+    rapier_config.physics_pipeline_active = false;
+}
+
+fn resume_physics_system(mut rapier_config: ResMut<RapierConfiguration>) {
+    rapier_config.physics_pipeline_active = true;
+}
+
+fn spawn_pause_menu_system(mut commands: Commands, asset_server: Res<AssetServer>) {
+    spawn_pause_menu(&mut commands, &asset_server);
+}
+
+fn respawn_pause_menu_system(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    gameplay_substate: Res<State<GameplayStateSubstates>>,
+) {
+    // Only bring the pause menu back if the settings menu was opened from it
+    if *gameplay_substate.current() == GameplayStateSubstates::Paused {
+        spawn_pause_menu(&mut commands, &asset_server);
+    }
+}
+
+fn spawn_pause_menu(commands: &mut Commands, asset_server: &AssetServer) {
+    let font = asset_server.load("papercut.ttf");
+
+    let root = spawn_menu_root(commands, PauseMenu);
+    commands.entity(root).with_children(|parent| {
+        spawn_menu_title(parent, &font, "PAUSED");
+        spawn_menu_button(parent, &font, "RESUME", PauseMenuButton::Resume);
+        spawn_menu_button(parent, &font, "RESTART", PauseMenuButton::Restart);
+        spawn_menu_button(parent, &font, "SETTINGS", PauseMenuButton::Settings);
+        spawn_menu_button(parent, &font, "MAIN MENU", PauseMenuButton::MainMenu);
+    });
+}
+
+fn pause_menu_button_system(
+    button_query: Query<(&Interaction, &PauseMenuButton), Changed<Interaction>>,
+    mut gameplay_substate: ResMut<State<GameplayStateSubstates>>,
+    mut settings_menu_state: ResMut<State<SettingsMenuState>>,
+    mut app_state: ResMut<State<AppState>>,
+    mut restart_requested: ResMut<RestartRequested>,
+) {
+    for (interaction, button) in button_query.iter() {
+        if *interaction != Interaction::Clicked {
+            continue;
+        }
+
+        match button {
+            PauseMenuButton::Resume => {
+                let _ = gameplay_substate.pop();
+            }
+            PauseMenuButton::Restart => {
+                // Restarting works like the respawn key once the run resumes
+                let _ = gameplay_substate.pop();
+                restart_requested.0 = true;
+            }
+            PauseMenuButton::Settings => {
+                let _ = settings_menu_state.set(SettingsMenuState::Open);
+            }
+            PauseMenuButton::MainMenu => {
+                // Leaving "Gameplay" tears the paused run down
+                let _ = app_state.set(AppState::MainMenu);
+            }
+        }
+    }
+}
+
+fn despawn_pause_menu_system(
+    mut commands: Commands,
+    pause_menu_query: Query<Entity, With<PauseMenu>>,
+) {
+    for entities in pause_menu_query.iter() {
+        commands.entity(entities).despawn_recursive();
+    }
+}
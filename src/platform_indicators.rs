@@ -1,21 +1,21 @@
+use crate::platforms::{CrumbleState, CrumblingPlatform};
 use crate::player::Player;
+use crate::GameplayCleanup;
 use crate::Platform;
-use crate::{platforms::PLATFORM_HEIGHT, platforms::PLATFORM_WIDTH, GameplayStateSubstates};
+use crate::{platforms::PLATFORM_HEIGHT, GameplayStateSubstates};
 use bevy::prelude::*;
 
 pub struct PlatformIndicator;
 
+// Sits under the platform it belongs to
 #[derive(Component)]
-struct PlatformCollisionIndicator;
+struct PlatformCollisionIndicator(Entity);
 
 impl Plugin for PlatformIndicator {
     fn build(&self, app: &mut App) {
         app.add_system_set(
-            SystemSet::on_exit(GameplayStateSubstates::PreGame)
-                .with_system(spawn_platform_collision_indicators),
-        )
-        .add_system_set(
             SystemSet::on_update(GameplayStateSubstates::DuringGame)
+                .with_system(spawn_platform_collision_indicators)
                 .with_system(update_indicator_position)
                 .with_system(update_indicator_color),
         )
@@ -27,18 +27,19 @@ impl Plugin for PlatformIndicator {
 }
 
 // Spawns a visual indicator for platforms that have been collided with
+// Runs every frame, as endless towers keep spawning platforms during the run
 fn spawn_platform_collision_indicators(
     mut commands: Commands,
-    platform_query: Query<&Transform, With<Platform>>,
+    platform_query: Query<(Entity, &Transform, &Platform), Added<Platform>>,
 ) {
     // Iterate through all platforms with the Platform component
-    for platfroms in platform_query.iter() {
+    for (platform_entity, platfroms, platform) in platform_query.iter() {
         // Spawn a sprite for each platform, with a specified color, size, and position
         commands.spawn((
             SpriteBundle {
                 sprite: Sprite {
                     color: Color::hex("ffffff").unwrap(),
-                    custom_size: Some(Vec2::new(PLATFORM_WIDTH * 1.1, PLATFORM_HEIGHT / 6.0)),
+                    custom_size: Some(Vec2::new(platform.width * 1.1, PLATFORM_HEIGHT / 6.0)),
                     ..Default::default()
                 },
                 transform: Transform::from_xyz(
@@ -48,69 +49,63 @@ fn spawn_platform_collision_indicators(
                 ),
                 ..Default::default()
             },
-            PlatformCollisionIndicator,
+            PlatformCollisionIndicator(platform_entity),
+            GameplayCleanup,
         ));
     }
 }
 
 // Updates the position of the visual indicator to match the position of the platform
 fn update_indicator_position(
+    mut commands: Commands,
     platform_query: Query<&Transform, With<Platform>>,
     mut platform_collision_query: Query<
-        &mut Transform,
-        (With<PlatformCollisionIndicator>, Without<Platform>),
+        (Entity, &PlatformCollisionIndicator, &mut Transform),
+        Without<Platform>,
     >,
 ) {
-    // Create a vector of platform positions
-    let mut platform_positions_vector = Vec::new();
-
-    for platform_transform in platform_query.iter() {
-        platform_positions_vector.push(platform_transform.translation)
-    }
-
     // Iterate through all platform collision indicators and update their position
-    let mut index = 0;
-    for mut platform_collision_transform in platform_collision_query.iter_mut() {
-        index += 1;
+    for (indicator_entity, indicator, mut platform_collision_transform) in
+        platform_collision_query.iter_mut()
+    {
+        // The platform was despawned with the rest of its endless chunk
+        let Ok(platform_transform) = platform_query.get(indicator.0) else {
+            commands.entity(indicator_entity).despawn_recursive();
+            continue;
+        };
         platform_collision_transform.translation = Vec3::new(
-            platform_positions_vector[index - 1].x,
-            platform_positions_vector[index - 1].y + -PLATFORM_HEIGHT / 1.5,
+            platform_transform.translation.x,
+            platform_transform.translation.y + -PLATFORM_HEIGHT / 1.5,
             1.0,
         );
     }
 }
 
-
 // Updates the color of the visual indicator based on whether the platform has been collided with
+#[allow(clippy::type_complexity)]
 fn update_indicator_color(
-    platform_query: Query<&Platform, (With<Platform>, Without<Player>)>,
+    platform_query: Query<(&Platform, Option<&CrumblingPlatform>), Without<Player>>,
     mut platform_collision_query: Query<
-        &mut Sprite,
-        (
-            With<PlatformCollisionIndicator>,
-            Without<Platform>,
-            Without<Player>,
-        ),
+        (&PlatformCollisionIndicator, &mut Sprite),
+        (Without<Platform>, Without<Player>),
     >,
 ) {
-    // Create a vector of platforms
-    let mut platform_color_vector = Vec::new();
-
-    for platforms in platform_query.iter() {
-        platform_color_vector.push(platforms);
-    }
-
     // Iterate through all platform collision indicators and update their color
-    let mut index = 0;
-    for mut platform_collision_sprites in platform_collision_query.iter_mut() {
-        index += 1;
-        if platform_color_vector[index - 1].already_collided  {
+    for (indicator, mut platform_collision_sprites) in platform_collision_query.iter_mut() {
+        let Ok((platform, crumbling_platform)) = platform_query.get(indicator.0) else {
+            continue;
+        };
+        platform_collision_sprites.color = match crumbling_platform.map(|crumbling| crumbling.state)
+        {
+            // Set the color to yellow while a crumbling platform is about to break
+            Some(CrumbleState::Shaking) => Color::YELLOW,
+            // Set the color to gray once it has broken away
+            Some(CrumbleState::Broken) => Color::GRAY,
             // Set the color to green if the platform has been collided with
-            platform_collision_sprites.color = Color::GREEN;
-        } else {
+            _ if platform.already_collided => Color::GREEN,
             // Set the color to red if the platform has not been collided with
-            platform_collision_sprites.color = Color::ORANGE_RED;
-        }
+            _ => Color::ORANGE_RED,
+        };
     }
 }
 
@@ -124,4 +119,4 @@ fn despawn_platform_collision_indicators(
         // Despawn the entity and all its children
         commands.entity(entities).despawn_recursive();
     }
-}
\ No newline at end of file
+}
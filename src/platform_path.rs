@@ -0,0 +1,154 @@
+use crate::gameplay_state::{FixedStepAppExt, FIXED_TIMESTEP};
+use crate::Platform;
+use bevy::prelude::*;
+use serde::Deserialize;
+
+pub struct PlatformPathPlugin;
+
+// A route for a platform to travel, read from the `path` of a floor in a tower layout
+#[derive(Deserialize, Debug, Clone)]
+pub struct PlatformPath {
+    // Offsets from the floor's position, the platform starts on the first one
+    pub waypoints: Vec<(f32, f32)>,
+    // Units per second, averaged over each leg when it is eased
+    pub speed: f32,
+    #[serde(default)]
+    pub easing: Easing,
+    #[serde(default)]
+    pub mode: PathMode,
+}
+
+// How the platform speeds up and slows down between two waypoints
+#[derive(Deserialize, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Easing {
+    // A constant speed, the right choice for circles and other smooth loops
+    #[default]
+    Linear,
+    // Starts and stops gently at every waypoint, like an elevator
+    EaseInOut,
+}
+
+#[derive(Deserialize, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum PathMode {
+    // Travels to the last waypoint and then back the same way
+    #[default]
+    PingPong,
+    // Travels from the last waypoint straight back to the first one and starts over
+    Loop,
+}
+
+// Where a platform is along its path
+#[derive(Component)]
+pub struct PathFollower {
+    path: PlatformPath,
+    origin: Vec2,
+    leg: usize,
+    // How far along the current leg the platform is, from 0 to 1
+    progress: f32,
+    forward: bool,
+}
+
+impl Plugin for PlatformPathPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_fixed_step_system_set(SystemSet::new().with_system(follow_path_system));
+    }
+}
+
+impl PlatformPath {
+    fn leg_count(&self) -> usize {
+        match self.mode {
+            PathMode::PingPong => self.waypoints.len() - 1,
+            PathMode::Loop => self.waypoints.len(),
+        }
+    }
+
+    fn leg(&self, leg: usize) -> (Vec2, Vec2) {
+        let start = self.waypoints[leg];
+        let end = self.waypoints[(leg + 1) % self.waypoints.len()];
+        (Vec2::new(start.0, start.1), Vec2::new(end.0, end.1))
+    }
+
+    // A path needs somewhere to go and a way to get there, every leg has to have a length so the
+    // platform never gets stuck on one
+    pub fn is_valid(&self) -> bool {
+        self.waypoints.len() >= 2
+            && self.speed > 0.0
+            && (0..self.leg_count()).all(|leg| {
+                let (start, end) = self.leg(leg);
+                start.distance(end) > 0.0
+            })
+    }
+}
+
+impl Easing {
+    fn apply(&self, progress: f32) -> f32 {
+        match self {
+            Easing::Linear => progress,
+            Easing::EaseInOut => progress * progress * (3.0 - 2.0 * progress),
+        }
+    }
+}
+
+impl PathFollower {
+    pub fn new(path: PlatformPath, origin: Vec2) -> Self {
+        PathFollower {
+            path,
+            origin,
+            leg: 0,
+            progress: 0.0,
+            forward: true,
+        }
+    }
+
+    pub fn position(&self) -> Vec2 {
+        let (start, end) = self.path.leg(self.leg);
+        // Coming back the other way a ping-pong leg is travelled from its end
+        let progress = if self.forward {
+            self.progress
+        } else {
+            1.0 - self.progress
+        };
+        self.origin + start.lerp(end, self.path.easing.apply(progress))
+    }
+
+    // Moves the platform the given distance along its path, turning around or starting over at the end
+    fn advance(&mut self, mut distance: f32) {
+        loop {
+            let (start, end) = self.path.leg(self.leg);
+            let length = start.distance(end);
+            let remaining = (1.0 - self.progress) * length;
+            if distance < remaining {
+                self.progress += distance / length;
+                return;
+            }
+
+            distance -= remaining;
+            self.progress = 0.0;
+            let last_leg = self.path.leg_count() - 1;
+            match self.path.mode {
+                PathMode::Loop => self.leg = (self.leg + 1) % self.path.leg_count(),
+                PathMode::PingPong if self.forward && self.leg < last_leg => self.leg += 1,
+                PathMode::PingPong if !self.forward && self.leg > 0 => self.leg -= 1,
+                PathMode::PingPong => self.forward = !self.forward,
+            }
+        }
+    }
+}
+
+// Moves platforms along their paths, they are kinematic so the physics carries the player along
+fn follow_path_system(
+    mut platform_query: Query<(&mut PathFollower, &mut Platform, &mut Transform)>,
+) {
+    for (mut path_follower, mut platform_object, mut platform_transform) in
+        platform_query.iter_mut()
+    {
+        let previous_position = path_follower.position();
+        let distance = path_follower.path.speed * FIXED_TIMESTEP;
+        path_follower.advance(distance);
+        let position = path_follower.position();
+
+        platform_object.velocity = (position - previous_position) / FIXED_TIMESTEP;
+        platform_transform.translation.x = position.x;
+        platform_transform.translation.y = position.y;
+    }
+}
@@ -1,175 +1,600 @@
-use crate::{platform_indicators::PlatformIndicator, GameplayStateSubstates, WindowDimensions};
+use crate::checkpoints::{Checkpoint, CHECKPOINT_COLOR};
+use crate::coins::spawn_coin;
+use crate::difficulty::{Difficulty, DifficultyPlugin, DifficultyProfile};
+use crate::endless::EndlessPlugin;
+use crate::gameplay_state::{FixedStepAppExt, FIXED_TIMESTEP};
+use crate::platform_path::{PathFollower, PlatformPathPlugin};
+use crate::player::PlayerGroundDetection;
+use crate::reachability::{floor_reachable, JumpArc};
+use crate::tower_layout::{
+    FloorLayout, TowerLayout, TowerLayoutHandle, TowerLayoutPlugin, DEFAULT_PLATFORM_TEXTURE,
+};
+use crate::{command_line_value, platform_indicators::PlatformIndicator};
+use crate::{GameMode, GameplayCleanup, GameplayStateSubstates, WindowDimensions, PLAYER_SIZE};
+use bevy::asset::LoadState;
 use bevy::prelude::*;
 use bevy_rapier2d::prelude::*;
-use rand::Rng;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use serde::Deserialize;
+use std::time::Duration;
 
 pub struct PlatformsPlugin;
 
 pub const PLATFORM_WIDTH: f32 = 64.0 * 1.875;
 pub const PLATFORM_HEIGHT: f32 = 32.0 * 0.625;
 
-#[derive(PartialEq, Eq)]
+// Seconds a crumbling platform holds once it has been landed on, unless its floor says otherwise
+pub const DEFAULT_CRUMBLE_DELAY: f32 = 0.6;
+const CRUMBLE_SHAKE_DISTANCE: f32 = 3.0;
+const CRUMBLE_SHAKE_SPEED: f32 = 60.0;
+
+const ONE_WAY_ALPHA: f32 = 0.6;
+
+// How many times a generated floor the player cannot get to is rolled again before it is given up on
+const MAX_FLOOR_ATTEMPTS: usize = 20;
+
+// The chance of a generated floor having a coin over it
+const COIN_CHANCE: f32 = 0.35;
+// How high above its floor a coin floats, within the bounce off the floor and under the next one
+const COIN_MIN_HEIGHT: f32 = PLAYER_SIZE * 0.9;
+const COIN_MAX_HEIGHT: f32 = PLAYER_SIZE * 1.6;
+
+// Holds a 64x32 region for every surface other than `Normal`, in the order they are declared
+pub const SURFACE_TEXTURE: &str = "PlatformSurfaces.png";
+
+// Bumped whenever the same seed starts generating a different tower, runs on older seeds are then marked as such
+pub const TOWER_VERSION: u32 = 1;
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
 pub enum PlatformType {
     Undefined,
     Stationary,
     Moving,
+    // Stays put until it is landed on, then shakes and breaks away
+    Crumbling,
+    // Follows the waypoints of its floor's path
+    Path,
+}
+
+// What the top of a platform does to the player standing on it
+#[derive(Deserialize, Debug, Default, Clone, Copy, PartialEq)]
+pub enum Surface {
+    #[default]
+    Normal,
+    // Multiplies the player's jump force
+    Bouncy,
+    // The player keeps their momentum instead of stopping as soon as the input changes
+    Ice,
+    // Pushes a grounded player sideways, negative speeds push to the left
+    Conveyor {
+        speed: f32,
+    },
 }
 
 #[derive(Component)]
 pub struct Platform {
+    // The platform's place in the tower, counting up from the starting platform
+    pub floor: usize,
     pub already_collided: bool,
     pub platform_type: PlatformType,
+    pub surface: Surface,
+    // How fast the platform moved on the last step, passed on to the player standing on it
+    pub velocity: Vec2,
+    pub one_way: bool,
+    pub width: f32,
     direction: f32,
     platform_moving_speed: f32,
+    left_bound: f32,
+    right_bound: f32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CrumbleState {
+    Intact,
+    Shaking,
+    Broken,
+}
+
+// A crumbling platform's countdown, it loses its collider once the countdown runs out
+#[derive(Component)]
+pub struct CrumblingPlatform {
+    pub state: CrumbleState,
+    timer: Timer,
+    // Where the platform sits when it is not shaking
+    rest_x: f32,
+    // The width of the collider it gets back when it is restored
+    width: f32,
 }
 
 #[derive(Resource)]
 pub struct SpawnCount(pub i8);
 
+// The seed every procedural choice of the tower is derived from
+#[derive(Resource, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LevelSeed(pub u64);
+
+impl LevelSeed {
+    // Reads the seed from `--seed <value>` on the command line, falling back to the
+    // `FLOOR_FIFTY_SEED` environment variable
+    pub fn from_env() -> Option<Self> {
+        command_line_value("--seed")
+            .or_else(|| std::env::var("FLOOR_FIFTY_SEED").ok())
+            .and_then(|value| Self::parse(&value))
+    }
+
+    pub fn random() -> Self {
+        LevelSeed(rand::thread_rng().gen())
+    }
+
+    fn parse(value: &str) -> Option<Self> {
+        match value.trim().parse() {
+            Ok(seed) => Some(LevelSeed(seed)),
+            Err(_) => {
+                warn!("Ignoring invalid level seed {:?}", value);
+                None
+            }
+        }
+    }
+}
+
+impl Surface {
+    // The texture a platform is drawn with and the part of it that is used, a floor's own texture
+    // replaces the surface's one
+    fn texture<'a>(&self, floor_texture: Option<&'a str>) -> (&'a str, Rect) {
+        let (texture, column) = match (floor_texture, self) {
+            (Some(texture), _) => (texture, 0.0),
+            (None, Surface::Normal) => (DEFAULT_PLATFORM_TEXTURE, 0.0),
+            (None, Surface::Bouncy) => (SURFACE_TEXTURE, 0.0),
+            (None, Surface::Ice) => (SURFACE_TEXTURE, 1.0),
+            (None, Surface::Conveyor { .. }) => (SURFACE_TEXTURE, 2.0),
+        };
+
+        let rect = Rect {
+            min: Vec2::new(64.0 * column, 0.0),
+            max: Vec2::new(64.0 * (column + 1.0), 32.0),
+        };
+        (texture, rect)
+    }
+}
+
+impl CrumblingPlatform {
+    // Puts a crumbled platform back together, used when the run is reset
+    pub fn restore(&mut self, commands: &mut Commands, platform_entity: Entity) {
+        if self.state == CrumbleState::Broken {
+            commands
+                .entity(platform_entity)
+                .insert(platform_collider(self.width));
+        }
+        self.state = CrumbleState::Intact;
+        self.timer.reset();
+    }
+}
+
 impl Plugin for PlatformsPlugin {
     fn build(&self, app: &mut App) {
         app.insert_resource(SpawnCount(19))
+            .insert_resource(LevelSeed::from_env().unwrap_or_else(LevelSeed::random))
+            .add_plugin(TowerLayoutPlugin)
             .add_plugin(PlatformIndicator)
-            .add_state(GameplayStateSubstates::PreGame)
+            .add_plugin(PlatformPathPlugin)
+            .add_plugin(DifficultyPlugin)
+            .add_plugin(EndlessPlugin)
+            .add_state(GameplayStateSubstates::Inactive)
             .add_system_set(
-                SystemSet::on_enter(GameplayStateSubstates::PreGame)
-                    .with_system(spawn_initial_platform_system)
-                    .with_system(spawn_platform_batch),
+                SystemSet::on_update(GameplayStateSubstates::PreGame)
+                    .with_system(spawn_tower_system),
             )
-            .add_system_set(
-                SystemSet::on_update(GameplayStateSubstates::DuringGame)
-                    .with_system(platform_properties_system),
+            .add_fixed_step_system_set(
+                SystemSet::new()
+                    .with_system(platform_properties_system)
+                    .with_system(crumble_trigger_system)
+                    .with_system(crumbling_platform_system.after(crumble_trigger_system)),
             );
     }
 }
 
-fn spawn_initial_platform_system(
+// Spawns the tower once, either from the layout file passed with `--level` or procedurally from the level seed
+#[allow(clippy::too_many_arguments)]
+fn spawn_tower_system(
     mut commands: Commands,
     window: Res<WindowDimensions>,
+    spawn_count: Res<SpawnCount>,
+    level_seed: Res<LevelSeed>,
+    difficulty: Res<Difficulty>,
+    game_mode: Res<GameMode>,
     asset_server: Res<AssetServer>,
+    tower_layout_handle: Res<TowerLayoutHandle>,
+    tower_layouts: Res<Assets<TowerLayout>>,
+    platform_query: Query<(), With<Platform>>,
 ) {
-    commands.spawn((
+    // The tower has already been spawned for this run, endless towers are streamed in by `EndlessPlugin`
+    if !platform_query.is_empty() || *game_mode == GameMode::Endless {
+        return;
+    }
+
+    let floors = match &tower_layout_handle.0 {
+        Some(handle) => match asset_server.get_load_state(handle) {
+            LoadState::Loaded => tower_layouts.get(handle).unwrap().floors.clone(),
+            // The asset server has already logged why the file could not be loaded
+            LoadState::Failed => {
+                error!("Falling back to a procedural tower, the tower layout could not be loaded");
+                generate_tower(&window, spawn_count.0, *level_seed, *difficulty)
+            }
+            // Wait for the layout to finish loading
+            _ => return,
+        },
+        None => generate_tower(&window, spawn_count.0, *level_seed, *difficulty),
+    };
+
+    for (index, floor) in floors.iter().enumerate() {
+        spawn_platform(&mut commands, &asset_server, &window, index, floor);
+    }
+}
+
+// Generates the starting platform followed by a batch of randomly placed platforms
+pub fn generate_tower(
+    window: &WindowDimensions,
+    spawn_count: i8,
+    level_seed: LevelSeed,
+    difficulty: Difficulty,
+) -> Vec<FloorLayout> {
+    let profile = difficulty.profile();
+
+    // Initialize a random number generator from the level seed so the same seed always builds the same tower
+    info!(
+        "Generating {:?} tower with seed {}",
+        difficulty, level_seed.0
+    );
+    let mut rng = StdRng::seed_from_u64(level_seed.0);
+
+    let mut floors = vec![starting_floor(window)];
+
+    // Iterate through the number of platforms to be spawned
+    for index in 1..(spawn_count + 1) {
+        // How far up the tower the floor is, everything gets harder towards the top
+        let height = index as f32 / spawn_count as f32;
+        let mut floor =
+            generate_reachable_floor(&mut rng, window, profile, height, floors.last().unwrap());
+        floor.checkpoint = profile
+            .checkpoint_interval
+            .is_some_and(|interval| index as u32 % interval == 0);
+        floors.push(floor);
+    }
+
+    let mut coin_rng = coin_rng(level_seed);
+    for floor in floors.iter_mut().skip(1) {
+        floor.coin = roll_coin(&mut coin_rng);
+    }
+
+    floors
+}
+
+// Coins are rolled from a generator of their own, so adding them left the floors of every seed as they were
+pub fn coin_rng(level_seed: LevelSeed) -> StdRng {
+    StdRng::seed_from_u64(level_seed.0.rotate_left(32))
+}
+
+// Rolls whether a coin floats over a floor, and where. It can be off to either side of the platform,
+// so collecting it is a detour
+pub fn roll_coin(rng: &mut StdRng) -> Option<(f32, f32)> {
+    if rng.gen::<f32>() >= COIN_CHANCE {
+        return None;
+    }
+    Some((
+        rng.gen_range(-PLATFORM_WIDTH..PLATFORM_WIDTH),
+        rng.gen_range(COIN_MIN_HEIGHT..COIN_MAX_HEIGHT),
+    ))
+}
+
+// The platform every tower starts from, it sits in the middle of the screen
+pub fn starting_floor(window: &WindowDimensions) -> FloorLayout {
+    FloorLayout {
+        x: 0.0,
+        y: -window.height / 4.0,
+        platform_type: PlatformType::Undefined,
+        speed: 0.0,
+        bounds: None,
+        texture: None,
+        crumble_delay: None,
+        surface: Surface::Normal,
+        path: None,
+        one_way: false,
+        width: None,
+        checkpoint: false,
+        coin: None,
+    }
+}
+
+// Rolls the floor above `below`. A floor the player cannot get to is rolled again, if that keeps
+// failing a plain see-through floor goes straight above the one below instead
+pub fn generate_reachable_floor(
+    rng: &mut StdRng,
+    window: &WindowDimensions,
+    profile: &DifficultyProfile,
+    height: f32,
+    below: &FloorLayout,
+) -> FloorLayout {
+    let y = below.y + window.height * profile.spacing.at(height);
+    (0..MAX_FLOOR_ATTEMPTS)
+        .map(|_| generate_floor(rng, window, profile, height, y))
+        .find(|floor| floor_reachable(&JumpArc::PLAYER, window, below, floor))
+        .unwrap_or_else(|| FloorLayout {
+            x: below.x,
+            y,
+            platform_type: PlatformType::Stationary,
+            speed: 0.0,
+            bounds: None,
+            texture: None,
+            crumble_delay: None,
+            surface: Surface::Normal,
+            path: None,
+            one_way: true,
+            width: Some(PLATFORM_WIDTH * profile.platform_width.at(height)),
+            checkpoint: false,
+            coin: None,
+        })
+}
+
+// Rolls a single floor at the given height of the tower
+fn generate_floor(
+    rng: &mut StdRng,
+    window: &WindowDimensions,
+    profile: &DifficultyProfile,
+    height: f32,
+    y: f32,
+) -> FloorLayout {
+    // Narrower platforms can get closer to the edges of the window
+    let width = PLATFORM_WIDTH * profile.platform_width.at(height);
+    let x = rng.gen_range(-(window.width / 2.0 - width)..(window.width / 2.0 - width));
+
+    let (mut platform_type, speed) = if rng.gen::<f32>() < profile.moving_chance.at(height) {
+        let speed = rng.gen_range(profile.min_speed.at(height)..profile.max_speed.at(height));
+        (PlatformType::Moving, speed)
+    } else {
+        (PlatformType::Stationary, 0.0)
+    };
+
+    // Special floors are split evenly between crumbling platforms and the special surfaces
+    let mut surface = Surface::Normal;
+    if rng.gen::<f32>() < profile.special_chance.at(height) {
+        match rng.gen_range(0..4) {
+            0 => platform_type = PlatformType::Crumbling,
+            1 => surface = Surface::Bouncy,
+            2 => surface = Surface::Ice,
+            _ => {
+                surface = Surface::Conveyor {
+                    speed: rng.gen_range(60.0..120.0) * if rng.gen::<bool>() { 1.0 } else { -1.0 },
+                }
+            }
+        }
+    }
+
+    // Some platforms can be jumped through from below
+    let one_way = rng.gen_range(0..3) == 0;
+
+    FloorLayout {
+        x,
+        y,
+        platform_type,
+        speed: if platform_type == PlatformType::Moving {
+            speed
+        } else {
+            0.0
+        },
+        bounds: None,
+        texture: None,
+        crumble_delay: None,
+        surface,
+        path: None,
+        one_way,
+        width: Some(width),
+        checkpoint: false,
+        coin: None,
+    }
+}
+
+// The left and right limits of a moving platform, the floor's own bounds or else the edges of the window
+pub fn moving_bounds(window: &WindowDimensions, floor: &FloorLayout, width: f32) -> (f32, f32) {
+    floor
+        .bounds
+        .unwrap_or((-(window.width / 2.0 - width), window.width / 2.0 - width))
+}
+
+// Spawns a single platform described by a floor layout
+pub fn spawn_platform(
+    commands: &mut Commands,
+    asset_server: &AssetServer,
+    window: &WindowDimensions,
+    index: usize,
+    floor: &FloorLayout,
+) -> Entity {
+    let width = floor.width.unwrap_or(PLATFORM_WIDTH);
+
+    let (left_bound, right_bound) = moving_bounds(window, floor, width);
+
+    let (texture, rect) = floor.surface.texture(floor.texture.as_deref());
+
+    // A path platform starts on the first waypoint of its path
+    let path_follower = match (floor.platform_type, &floor.path) {
+        (PlatformType::Path, Some(path)) => {
+            Some(PathFollower::new(path.clone(), Vec2::new(floor.x, floor.y)))
+        }
+        _ => None,
+    };
+    let position = path_follower
+        .as_ref()
+        .map_or(Vec2::new(floor.x, floor.y), PathFollower::position);
+
+    let rigid_body = match floor.platform_type {
+        PlatformType::Moving | PlatformType::Path => RigidBody::KinematicPositionBased,
+        _ => RigidBody::Fixed,
+    };
+
+    let mut color = if floor.checkpoint {
+        CHECKPOINT_COLOR
+    } else {
+        Color::WHITE
+    };
+    // One-way platforms are see-through, so they read as something that can be passed
+    if floor.one_way {
+        color.set_a(ONE_WAY_ALPHA);
+    }
+
+    let mut platform = commands.spawn((
         SpriteBundle {
             sprite: Sprite {
-                custom_size: Some(Vec2::new(PLATFORM_WIDTH, PLATFORM_HEIGHT)),
-                rect: Some(Rect {
-                    min: Vec2::new(0.0, 0.0),
-                    max: Vec2::new(64.0, 32.0),
-                }),
+                color,
+                custom_size: Some(Vec2::new(width, PLATFORM_HEIGHT)),
+                rect: Some(rect),
                 ..Default::default()
             },
-            transform: Transform::from_xyz(0.0, -window.height / 4.0, 0.0),
-            texture: asset_server.load("PlatformTexture2.png"),
+            transform: Transform::from_xyz(position.x, position.y, 0.0),
+            texture: asset_server.load(texture),
             ..Default::default()
         },
-        RigidBody::Fixed,
-        Collider::cuboid(PLATFORM_WIDTH / 2.0, PLATFORM_HEIGHT / 2.0),
+        // Moving platforms are kinematic so whatever stands on them is carried along
+        rigid_body,
+        platform_collider(width),
         Platform {
+            floor: index,
             already_collided: false,
             direction: 1.0,
-            platform_type: PlatformType::Undefined,
-            platform_moving_speed: 0.0,
+            platform_type: floor.platform_type,
+            surface: floor.surface,
+            velocity: Vec2::ZERO,
+            one_way: floor.one_way,
+            width,
+            platform_moving_speed: floor.speed,
+            left_bound,
+            right_bound,
         },
+        GameplayCleanup,
     ));
-}
 
-// Spawns a batch of platforms in the game
-fn spawn_platform_batch(
-    mut commands: Commands,
-    window: Res<WindowDimensions>,
-    spawn_count: Res<SpawnCount>,
-    asset_server: Res<AssetServer>,
-) {
-    // Calculate the left and right bounds for the platforms
-    let left_bound: f32 = -(window.width / 2.0 - PLATFORM_WIDTH);
-    let right_bound: f32 = window.width / 2.0 - PLATFORM_WIDTH;
+    // Only one-way platforms need the contact modification hook
+    if floor.one_way {
+        platform.insert(ActiveHooks::MODIFY_SOLVER_CONTACTS);
+    }
+
+    if let Some(path_follower) = path_follower {
+        platform.insert(path_follower);
+    }
 
-    // Calculate the spacing between the platforms
-    let spacing: f32 = window.height / 4.2;
+    if floor.checkpoint {
+        platform.insert(Checkpoint);
+    }
 
-    // Initialize a random number generator
-    let mut rng = rand::thread_rng();
+    if let Some((x, y)) = floor.coin {
+        platform.with_children(|parent| spawn_coin(parent, Vec2::new(x, y)));
+    }
 
-    // Iterate through the number of platforms to be spawned
-    for index in 1..(spawn_count.0 + 1) {
-        // Spawn the platform sprite with a random position within the bounds and specified texture
-        let platform = commands
-            .spawn((
-                SpriteBundle {
-                    sprite: Sprite {
-                        custom_size: Some(Vec2::new(PLATFORM_WIDTH, PLATFORM_HEIGHT)),
-                        rect: Some(Rect {
-                            min: Vec2::new(0.0, 0.0),
-                            max: Vec2::new(64.0, 32.0),
-                        }),
-                        ..Default::default()
-                    },
-                    transform: Transform::from_xyz(
-                        rng.gen_range(left_bound..right_bound),
-                        -window.height / 4.0 + (spacing * index as f32),
-                        0.0,
-                    ),
-                    texture: asset_server.load("PlatformTexture2.png"),
-                    ..Default::default()
-                },
-                // Add a fixed rigid body component
-                RigidBody::Fixed,
-                // Add a cuboid collider component with specified dimensions
-                Collider::cuboid(PLATFORM_WIDTH / 2.0, PLATFORM_HEIGHT / 2.0),
-            ))
-            .id();
-
-        // Generate random values for the platform type and speed
-        let plat_type_rng_value = rng.gen_range(0..200);
-        let plat_speed_rng_value = rng.gen_range(100.0..200.0);
-
-        // Insert the Platform component based on the random values
-        if plat_type_rng_value % 2 == 0 {
-            commands.entity(platform).insert(Platform {
-                already_collided: false,
-                direction: 1.0,
-                platform_type: PlatformType::Moving,
-                platform_moving_speed: plat_speed_rng_value,
-            });
-        } else if plat_type_rng_value % 2 != 0 {
-            commands.entity(platform).insert(Platform {
-                already_collided: false,
-                direction: 1.0,
-                platform_type: PlatformType::Stationary,
-                platform_moving_speed: 0.0,
-            });
-        }
+    if floor.platform_type == PlatformType::Crumbling {
+        platform.insert(CrumblingPlatform {
+            state: CrumbleState::Intact,
+            timer: Timer::from_seconds(
+                floor.crumble_delay.unwrap_or(DEFAULT_CRUMBLE_DELAY),
+                TimerMode::Once,
+            ),
+            rest_x: floor.x,
+            width,
+        });
     }
+
+    platform.id()
+}
+
+// The collider every platform is spawned with, and that crumbled platforms get back
+fn platform_collider(width: f32) -> Collider {
+    Collider::cuboid(width / 2.0, PLATFORM_HEIGHT / 2.0)
 }
 
 // Modifies the properties of moving platforms in the game
 fn platform_properties_system(
     mut platform_query: Query<(&mut Platform, &mut Transform), With<Platform>>,
-    time: Res<Time>,
-    window: Res<WindowDimensions>,
 ) {
-    // Calculate the left and right bounds for the platforms
-    let left_bound: f32 = -(window.width / 2.0 - PLATFORM_WIDTH);
-    let right_bound: f32 = window.width / 2.0 - PLATFORM_WIDTH;
-
     // Iterate through the platforms in the query
     for (mut platform_object, mut platform_transform) in platform_query.iter_mut() {
         // Check if the platform is a moving platform
         if platform_object.platform_type == PlatformType::Moving {
+            let previous_x = platform_transform.translation.x;
+
             // Update the position of the platform based on its speed and direction
-            platform_transform.translation.x += platform_object.platform_moving_speed
-                * time.delta_seconds()
-                * platform_object.direction;
+            platform_transform.translation.x +=
+                platform_object.platform_moving_speed * FIXED_TIMESTEP * platform_object.direction;
 
             // Check if the platform has reached the left or right bounds
-            if platform_transform.translation.x > right_bound {
-                platform_transform.translation.x = right_bound;
+            if platform_transform.translation.x > platform_object.right_bound {
+                platform_transform.translation.x = platform_object.right_bound;
                 platform_object.direction = -1.0;
             }
-            if platform_transform.translation.x < left_bound {
-                platform_transform.translation.x = left_bound;
+            if platform_transform.translation.x < platform_object.left_bound {
+                platform_transform.translation.x = platform_object.left_bound;
                 platform_object.direction = 1.0;
             }
+
+            platform_object.velocity.x =
+                (platform_transform.translation.x - previous_x) / FIXED_TIMESTEP;
+        }
+    }
+}
+
+// Starts the countdown of a crumbling platform once the player's ground sensor touches it
+fn crumble_trigger_system(
+    mut collision_events: EventReader<CollisionEvent>,
+    ground_detection_query: Query<(), With<PlayerGroundDetection>>,
+    mut crumbling_platform_query: Query<&mut CrumblingPlatform>,
+) {
+    for collision_event in collision_events.iter() {
+        let CollisionEvent::Started(first, second, _) = *collision_event else {
+            continue;
+        };
+
+        for (sensor, platform) in [(first, second), (second, first)] {
+            if !ground_detection_query.contains(sensor) {
+                continue;
+            }
+            if let Ok(mut crumbling_platform) = crumbling_platform_query.get_mut(platform) {
+                if crumbling_platform.state == CrumbleState::Intact {
+                    crumbling_platform.state = CrumbleState::Shaking;
+                }
+            }
         }
     }
 }
+
+// Shakes crumbling platforms while their countdown runs, and takes their collider away once it runs out
+fn crumbling_platform_system(
+    mut commands: Commands,
+    mut crumbling_platform_query: Query<(
+        Entity,
+        &mut CrumblingPlatform,
+        &mut Transform,
+        &mut Visibility,
+    )>,
+) {
+    for (platform_entity, mut crumbling_platform, mut platform_transform, mut visibility) in
+        crumbling_platform_query.iter_mut()
+    {
+        platform_transform.translation.x = crumbling_platform.rest_x;
+
+        if crumbling_platform.state == CrumbleState::Shaking {
+            // Uses the fixed timestep so the platform breaks on the same frame when a run is replayed
+            crumbling_platform
+                .timer
+                .tick(Duration::from_secs_f32(FIXED_TIMESTEP));
+
+            if crumbling_platform.timer.finished() {
+                crumbling_platform.state = CrumbleState::Broken;
+                // The handle goes too, so the collider is set up again from scratch when it is restored
+                commands
+                    .entity(platform_entity)
+                    .remove::<(Collider, RapierColliderHandle)>();
+            } else {
+                platform_transform.translation.x += CRUMBLE_SHAKE_DISTANCE
+                    * (crumbling_platform.timer.elapsed_secs() * CRUMBLE_SHAKE_SPEED).sin();
+            }
+        }
+
+        visibility.is_visible = crumbling_platform.state != CrumbleState::Broken;
+    }
+}
@@ -1,79 +1,159 @@
-use crate::GameplayStateSubstates;
-use crate::{DeathRegionReachedEvent, TopFloorReachedEvent};
+use crate::gameplay_state::{
+    DeathLine, FixedStepAppExt, FloorReachedEvent, RestartRequestedEvent, FIXED_TIMESTEP,
+};
+use crate::platforms::Surface;
+use crate::player_animation::{
+    spawn_player_sprite, PlayerAnimationPlugin, PlayerAnimations, PlayerAnimationsHandle,
+    PlayerState,
+};
+use crate::player_input::{PlayerInput, PlayerInputSampling};
+use crate::settings::GameSettings;
+use crate::{AppState, DeathRegionReachedEvent, GameMode, TopFloorReachedEvent};
+use crate::{GameplayCleanup, GameplayStateSubstates};
 use crate::{Platform, WindowDimensions};
 
 use bevy::prelude::*;
 use bevy_rapier2d::{prelude::*, rapier::prelude::CollisionEventFlags};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
 
 pub struct PlayerPlugin;
 
 pub const PLAYER_SIZE: f32 = 32.0 * 1.56;
+pub const PLAYER_RADIUS: f32 = PLAYER_SIZE / 1.7;
+// The ground sensor hangs below the player's collider, this is how far under their centre it reaches
+pub const GROUND_SENSOR_DEPTH: f32 = PLAYER_SIZE / 2.0 + PLAYER_SIZE / 9.0 * 2.0;
+
+// Shared with the tower generator, which checks every floor can be reached with them
+pub const MOVEMENT_SPEED: f32 = 356.0;
+pub const JUMP_FORCE: f32 = 268.2;
+// Gravity multipliers while going up and coming down with the jump button, heavier on the way down
+pub const RISE_GRAVITY_SCALE: f32 = 1.0;
+pub const FALL_GRAVITY_SCALE: f32 = 1.6;
+
+// How long the player still counts as grounded after leaving a platform, and how long a landing waits to bounce, in seconds
+pub const COYOTE_TIME: f32 = 0.1;
+pub const JUMP_BUFFER_TIME: f32 = 0.12;
+
+pub const BOUNCY_JUMP_MULTIPLIER: f32 = 1.5;
+// How much of the gap to the wanted speed is closed every step on ice, a normal surface closes all of it
+const ICE_GRIP: f32 = 0.04;
+
+// How the player leaves the ground, picked in the settings menu and fixed for the length of a run
+#[derive(Resource, Serialize, Deserialize, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum ControlMode {
+    // The classic controls, the player bounces at full height on every landing
+    #[default]
+    AutoBounce,
+    // The player jumps with the jump button, letting go early gives a smaller hop
+    ManualJump,
+}
 
 #[derive(Component)]
 pub struct Player {
     movement_speed: f32,
     jump_force: f32,
-    pub player_colliding: bool,
+    // How much of the upward speed is kept when the jump button is let go on the way up
+    jump_cut: f32,
+    // Gravity multipliers while going up and coming down, only used with the jump button
+    rise_gravity_scale: f32,
+    fall_gravity_scale: f32,
+    // Set while a jump started with the jump button can still be cut short
+    jump_held: bool,
+    // The number of platforms the ground sensor is touching
+    ground_contacts: u32,
+    // The platform last landed on, while the player is still standing on it
+    ground_platform: Option<Entity>,
+    // One-way platforms the ground sensor is passing through, they do not count as ground
+    passing_platforms: Vec<Entity>,
+    // The one-way platform the player is dropping through, until their body has left it
+    dropping_through: Option<Entity>,
+    // Runs for as long as the player still counts as grounded after leaving a platform
+    coyote_timer: Timer,
+    // Runs for as long as a landing waits for the player to be grounded before the bounce is dropped
+    jump_buffer_timer: Timer,
     pub player_grounded: bool,
+    // The surface of the platform last landed on, ice keeps its hold on the player until the next landing
+    pub surface: Surface,
     player_facing_right: bool,
-    pub score: i8,
+    pub score: u32,
 }
 
 #[derive(Component)]
-struct PlayerGroundDetection;
+pub struct PlayerGroundDetection;
 
 impl Plugin for PlayerPlugin {
     fn build(&self, app: &mut App) {
-        app.add_event::<TopFloorReachedEvent>()
-            .add_event::<DeathRegionReachedEvent>()
+        app.add_plugin(PlayerAnimationPlugin)
+            .init_resource::<ControlMode>()
+            .add_system_set(
+                SystemSet::on_enter(AppState::Gameplay).with_system(apply_control_mode_system),
+            )
             .add_system_set(
                 SystemSet::on_enter(GameplayStateSubstates::PreGame)
                     .with_system(spawn_player_system),
             )
-            .add_system_set(
-                SystemSet::on_update(GameplayStateSubstates::DuringGame)
-                    .with_system(player_input_system)
+            .add_fixed_step_system_set(
+                SystemSet::new()
+                    .with_system(
+                        player_input_system
+                            .after(PlayerInputSampling)
+                            .after(player_grounded_system),
+                    )
                     .with_system(player_screen_looping_system)
-                    .with_system(player_animation_system)
-                    .with_system(player_collision_detection_system),
+                    .with_system(player_collision_detection_system)
+                    .with_system(player_grounded_system.after(player_collision_detection_system)),
             );
     }
 }
 
-pub fn spawn_player_system(mut commands: Commands, asset_server: Res<AssetServer>) {
+pub fn spawn_player_system(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut texture_atlases: ResMut<Assets<TextureAtlas>>,
+    player_animations_handle: Res<PlayerAnimationsHandle>,
+    player_animations: Res<Assets<PlayerAnimations>>,
+) {
     let player = commands
         .spawn((
-            SpriteBundle {
-                sprite: Sprite {
-                    custom_size: Some(Vec2::new(PLAYER_SIZE, PLAYER_SIZE)),
-                    ..Default::default()
-                },
-                texture: asset_server.load("PlayerTexture.png"),
-                transform: Transform::from_xyz(0.0, -PLAYER_SIZE * 2.0, 0.0),
-                ..Default::default()
-            },
+            // The sprite is a child so it can be squashed and stretched without touching the collider
+            SpatialBundle::from_transform(Transform::from_xyz(0.0, -PLAYER_SIZE * 2.0, 0.0)),
             RigidBody::Dynamic,
             Velocity::zero(),
-            Collider::ball(PLAYER_SIZE / 1.7),
+            Collider::ball(PLAYER_RADIUS),
             ColliderMassProperties::Mass(3.85),
             ActiveEvents::COLLISION_EVENTS,
             LockedAxes::ROTATION_LOCKED,
+            GravityScale(1.0),
             (ActiveCollisionTypes::default() | ActiveCollisionTypes::DYNAMIC_KINEMATIC),
             Player {
-                movement_speed: 356.0,
-                jump_force: 268.2,
-                player_colliding: false,
+                movement_speed: MOVEMENT_SPEED,
+                jump_force: JUMP_FORCE,
+                jump_cut: 0.45,
+                rise_gravity_scale: RISE_GRAVITY_SCALE,
+                fall_gravity_scale: FALL_GRAVITY_SCALE,
+                jump_held: false,
+                ground_contacts: 0,
+                ground_platform: None,
+                passing_platforms: Vec::new(),
+                dropping_through: None,
+                coyote_timer: finished_timer(COYOTE_TIME),
+                jump_buffer_timer: finished_timer(JUMP_BUFFER_TIME),
                 player_grounded: false,
+                surface: Surface::Normal,
                 player_facing_right: true,
                 score: 0,
             },
+            PlayerState::default(),
+            GameplayCleanup,
         ))
         .id();
 
     let player_ground_detection = commands
         .spawn((
             Sensor,
-            Collider::cuboid(PLAYER_SIZE / 3.0, PLAYER_SIZE / 9.0),
+            // As wide as the player's collider, so landings on the very edge of a platform are caught
+            Collider::cuboid(PLAYER_RADIUS, PLAYER_SIZE / 9.0),
             ActiveEvents::COLLISION_EVENTS,
             (ActiveCollisionTypes::default() | ActiveCollisionTypes::KINEMATIC_KINEMATIC),
             TransformBundle {
@@ -84,80 +164,207 @@ pub fn spawn_player_system(mut commands: Commands, asset_server: Res<AssetServer
         ))
         .id();
 
+    let player_sprite = spawn_player_sprite(
+        &mut commands,
+        &asset_server,
+        &mut texture_atlases,
+        player_animations.get(&player_animations_handle.0),
+    );
+
     commands
         .entity(player)
-        .push_children(&[player_ground_detection]);
+        .push_children(&[player_ground_detection, player_sprite]);
+}
+
+// A control mode picked in the middle of a run only takes effect once the next one starts
+fn apply_control_mode_system(mut control_mode: ResMut<ControlMode>, settings: Res<GameSettings>) {
+    *control_mode = settings.control_mode;
+}
+
+impl Player {
+    // Touching a platform right now, unlike `player_grounded` which also covers coyote time
+    pub fn on_ground(&self) -> bool {
+        self.ground_contacts > 0
+    }
+
+    pub fn facing_right(&self) -> bool {
+        self.player_facing_right
+    }
+
+    pub fn dropping_through(&self) -> Option<Entity> {
+        self.dropping_through
+    }
+
+    // The upward speed of a jump from the surface the player is on
+    fn launch_force(&self) -> f32 {
+        match self.surface {
+            Surface::Bouncy => self.jump_force * BOUNCY_JUMP_MULTIPLIER,
+            _ => self.jump_force,
+        }
+    }
 }
 
+// A timer that has already run out, so a grace window is only open once something starts it
+fn finished_timer(seconds: f32) -> Timer {
+    let mut timer = Timer::from_seconds(seconds, TimerMode::Once);
+    timer.tick(timer.duration());
+    timer
+}
+
+#[allow(clippy::type_complexity)]
 fn player_input_system(
-    keyboard_input: Res<Input<KeyCode>>,
+    player_input: Res<PlayerInput>,
+    control_mode: Res<ControlMode>,
     mut player_query: Query<(
-        (&mut Player, &mut Velocity, &mut Transform, &GlobalTransform),
+        (
+            &mut Player,
+            &mut Velocity,
+            &mut Transform,
+            &GlobalTransform,
+            &mut GravityScale,
+        ),
         With<Player>,
     )>,
+    platform_query: Query<&Platform>,
+    death_line: Res<DeathLine>,
     mut failure_event: EventWriter<DeathRegionReachedEvent>,
+    mut restart_event: EventWriter<RestartRequestedEvent>,
 ) {
     // Get the mutable player component and velocity component
     let (mut player, _player_velocity) = player_query.single_mut();
 
-    // Check if the player is pressing the left or right movement keys
-    let left = keyboard_input.pressed(KeyCode::A) || keyboard_input.pressed(KeyCode::Left);
-    let right = keyboard_input.pressed(KeyCode::D) || keyboard_input.pressed(KeyCode::Right);
-    // Calculate the x input direction based on the left and right keys
-    let x_input = -(left as i8) + right as i8;
+    // Get how far the player is pushing left or right, partly deflected sticks land in between
+    let x_input = player_input.x_axis.clamp(-1.0, 1.0);
 
-    // Set the player's facing direction based on the movement keys
-    if right {
+    // Set the player's facing direction based on the movement input
+    if x_input > 0.0 {
         player.0.player_facing_right = true;
     }
-    if left {
+    if x_input < 0.0 {
         player.0.player_facing_right = false;
     }
 
-    // Calculate the player's input direction vector
-    let mut player_input_dir = Vec2::new(x_input as f32, 0.0);
-    if player_input_dir != Vec2::ZERO {
-        // Normalize the input direction vector
-        player_input_dir /= player_input_dir.length();
+    // Set the player's x velocity in proportion to the input and movement speed, ice only gets there gradually
+    let target_x_velocity = x_input * player.0.movement_speed;
+    match player.0.surface {
+        Surface::Ice => player.1.linvel.x += (target_x_velocity - player.1.linvel.x) * ICE_GRIP,
+        _ => player.1.linvel.x = target_x_velocity,
     }
 
-    // Set the player's x velocity based on the input direction and movement speed
-    player.1.linvel.x = player_input_dir.x * player.0.movement_speed;
+    // Conveyors only push the player while they are standing on one
+    if let Surface::Conveyor { speed } = player.0.surface {
+        if player.0.on_ground() {
+            player.1.linvel.x += speed;
+        }
+    }
 
-    // If the player is colliding with something, set the y velocity to the jump force
-    if player.0.player_colliding {
-        player.1.linvel.y = player.0.jump_force;
+    // A moving platform carries the player standing on it sideways, the physics already takes care of up and down
+    if let Some(platform) = player
+        .0
+        .ground_platform
+        .and_then(|platform_entity| platform_query.get(platform_entity).ok())
+    {
+        player.1.linvel.x += platform.velocity.x;
     }
 
+    // Dropping through the one-way platform underneath takes the place of jumping off it
+    if player_input.drop_through && player.0.on_ground() && player.0.dropping_through.is_none() {
+        if let Some(platform_entity) = player.0.ground_platform.filter(|platform_entity| {
+            platform_query
+                .get(*platform_entity)
+                .is_ok_and(|platform| platform.one_way)
+        }) {
+            player.0.dropping_through = Some(platform_entity);
+            use_up_grace_windows(&mut player.0);
+        }
+    }
+
+    match *control_mode {
+        // Nothing to jump off while dropping through a platform
+        _ if player.0.dropping_through.is_some() => {}
+        ControlMode::AutoBounce => {
+            // Bounce once a buffered landing meets the ground, as long as the player is not already on the way up
+            if !player.0.jump_buffer_timer.finished()
+                && player.0.player_grounded
+                && player.1.linvel.y <= 0.0
+            {
+                player.1.linvel.y = player.0.launch_force();
+                use_up_grace_windows(&mut player.0);
+            }
+        }
+        ControlMode::ManualJump => {
+            // A press is buffered, so pressing jump just before landing still counts
+            if player_input.jump_pressed {
+                player.0.jump_buffer_timer.reset();
+            }
+
+            if !player.0.jump_buffer_timer.finished() && player.0.player_grounded {
+                player.1.linvel.y = player.0.launch_force();
+                player.0.jump_held = true;
+                use_up_grace_windows(&mut player.0);
+            }
+
+            // Letting go of jump on the way up cuts the jump short
+            if player.0.jump_held && !player_input.jump && player.1.linvel.y > 0.0 {
+                player.1.linvel.y *= player.0.jump_cut;
+                player.0.jump_held = false;
+            }
+            if player.1.linvel.y <= 0.0 {
+                player.0.jump_held = false;
+            }
+        }
+    }
+
+    // Falling can be made heavier than rising, the classic bounce keeps plain gravity
+    let gravity_scale = match *control_mode {
+        ControlMode::AutoBounce => 1.0,
+        ControlMode::ManualJump if player.1.linvel.y > 0.0 => player.0.rise_gravity_scale,
+        ControlMode::ManualJump => player.0.fall_gravity_scale,
+    };
+    *player.4 = GravityScale(gravity_scale);
+
     // Check if the player is pressing the down key
-    let down = keyboard_input.pressed(KeyCode::S) || keyboard_input.pressed(KeyCode::Down);
+    let down = player_input.fast_fall;
     // If the player is pressing the down key, set the y velocity to a negative jump force
     if down {
         player.1.linvel.y = -player.0.jump_force * 5.0;
     }
 
     // Check if the player has just pressed the respawn key
-    let respawn = keyboard_input.just_pressed(KeyCode::R);
-    // If the player has pressed the respawn key, send the death region event
+    let respawn = player_input.restart;
+    // If the player has pressed the respawn key, start the tower over
     if respawn {
-        failure_event.send(DeathRegionReachedEvent);
+        restart_event.send(RestartRequestedEvent);
     }
 
     // If the player's y position is below the death region, send the death region event
-    if player.3.translation().y < -400.0 {
+    if player.3.translation().y < death_line.0 {
         failure_event.send(DeathRegionReachedEvent);
     }
 }
 
+// Uses up the buffered jump and the coyote time, so the same landing cannot jump twice
+fn use_up_grace_windows(player: &mut Player) {
+    let jump_buffer_duration = player.jump_buffer_timer.duration();
+    player.jump_buffer_timer.tick(jump_buffer_duration);
+    let coyote_duration = player.coyote_timer.duration();
+    player.coyote_timer.tick(coyote_duration);
+}
+
+#[allow(clippy::type_complexity, clippy::too_many_arguments)]
 fn player_collision_detection_system(
     mut collision_events: EventReader<CollisionEvent>,
     mut top_floor_reached_event: EventWriter<TopFloorReachedEvent>,
+    mut floor_reached_event: EventWriter<FloorReachedEvent>,
     player_ground_detection_query: Query<(
         (Entity, &mut PlayerGroundDetection),
         With<PlayerGroundDetection>,
     )>,
-    mut player_query: Query<((Entity, &mut Player), With<Player>)>,
+    mut player_query: Query<((Entity, &mut Player, &Velocity), With<Player>)>,
     mut platform_query: Query<(Entity, &mut Platform), With<Platform>>,
+    control_mode: Res<ControlMode>,
+    game_mode: Res<GameMode>,
+    rapier_context: Res<RapierContext>,
 ) {
     // Get the player entity and object
     let (mut player_entity, _player_object) = player_query.single_mut();
@@ -174,7 +381,8 @@ fn player_collision_detection_system(
     }
 
     // If the player's score is equal to the total number of platforms, send the top floor reached event
-    if player_entity.1.score == total_count {
+    // Endless towers keep growing, so there is no top floor to reach
+    if player_entity.1.score == total_count && *game_mode != GameMode::Endless {
         top_floor_reached_event.send(TopFloorReachedEvent);
     }
 
@@ -182,28 +390,7 @@ fn player_collision_detection_system(
     for collision_event in collision_events.iter() {
         // Iterate over the platform entities and objects
         for (platform_entity, mut platform_object) in platform_query.iter_mut() {
-            // If the collision event indicates that the player has started colliding with a platform, set the player colliding flag to true
-            if *collision_event
-                == CollisionEvent::Started(
-                    player_entity.0,
-                    platform_entity,
-                    CollisionEventFlags::from_bits(0).unwrap(),
-                )
-            {
-                player_entity.1.player_colliding = true;
-            } 
-            // If the collision event indicates that the player has stopped colliding with a platform, set the player colliding flag to false
-            else if *collision_event
-                == CollisionEvent::Stopped(
-                    player_entity.0,
-                    platform_entity,
-                    CollisionEventFlags::from_bits(0).unwrap(),
-                )
-            {
-                player_entity.1.player_colliding = false;
-            }
-
-            // If the collision event indicates that the player ground detection has started colliding with a platform, set the player grounded flag to true
+            // If the collision event indicates that the player ground detection has started colliding with a platform, count it as ground underneath the player
             if *collision_event
                 == CollisionEvent::Started(
                     player_ground_detection_entity.0,
@@ -211,25 +398,122 @@ fn player_collision_detection_system(
                     CollisionEventFlags::from_bits(1).unwrap(),
                 )
             {
-                player_entity.1.player_grounded = true;
-                // If the platform has not already been collided with, increase the player's score and set the platform's already collided flag to true
-                if !platform_object.already_collided {
-                    player_entity.1.score += 1;
-                    platform_object.already_collided = true;
+                // Passing up through a one-way platform, or dropping down through one, is not a landing
+                if platform_object.one_way
+                    && (player_entity.2.linvel.y > 0.0
+                        || player_entity.1.dropping_through == Some(platform_entity))
+                {
+                    player_entity.1.passing_platforms.push(platform_entity);
+                    continue;
                 }
-            } 
-            // If the collision event indicates that the player ground detection has stopped colliding with a platform, set the player grounded flag to false
-            else if *collision_event
-                == CollisionEvent::Stopped(
-                    player_ground_detection_entity.0,
+
+                land_on_platform(
+                    &mut player_entity.1,
                     platform_entity,
-                    CollisionEventFlags::from_bits(1).unwrap(),
+                    &mut platform_object,
+                    *control_mode,
+                    &mut floor_reached_event,
+                );
+            }
+            // If the collision event indicates that the player ground detection has stopped colliding with a platform, start the coyote time once no platform is left underneath
+            // A platform that crumbles away ends the contact too, with the removed flag set as well
+            else if matches!(
+                *collision_event,
+                CollisionEvent::Stopped(sensor, platform, flags)
+                    if sensor == player_ground_detection_entity.0
+                        && platform == platform_entity
+                        && flags.contains(CollisionEventFlags::SENSOR)
+            ) {
+                if let Some(index) = player_entity
+                    .1
+                    .passing_platforms
+                    .iter()
+                    .position(|passing_platform| *passing_platform == platform_entity)
+                {
+                    player_entity.1.passing_platforms.swap_remove(index);
+                    continue;
+                }
+
+                player_entity.1.ground_contacts = player_entity.1.ground_contacts.saturating_sub(1);
+                if player_entity.1.ground_platform == Some(platform_entity) {
+                    player_entity.1.ground_platform = None;
+                }
+                // Dropping through a platform is not walking off it, so there is no coyote time
+                if player_entity.1.ground_contacts == 0
+                    && player_entity.1.dropping_through != Some(platform_entity)
+                {
+                    player_entity.1.coyote_timer.reset();
+                }
+            }
+            // The drop is over once the player's body has left the platform it dropped through
+            else if player_entity.1.dropping_through == Some(platform_entity)
+                && matches!(
+                    *collision_event,
+                    CollisionEvent::Stopped(first, second, _)
+                        if (first, second) == (player_entity.0, platform_entity)
+                            || (second, first) == (player_entity.0, platform_entity)
                 )
             {
-                player_entity.1.player_grounded = false;
+                player_entity.1.dropping_through = None;
             }
         }
     }
+
+    // A one-way platform passed into on the way up is landed on once the player starts to come
+    // down while the ground sensor is still inside it, there is no new collision to tell
+    if player_entity.2.linvel.y > 0.0 {
+        return;
+    }
+    let passing_platforms = std::mem::take(&mut player_entity.1.passing_platforms);
+    for platform_entity in passing_platforms {
+        let landed = player_entity.1.dropping_through != Some(platform_entity)
+            && rapier_context.intersection_pair(player_ground_detection_entity.0, platform_entity)
+                == Some(true);
+        match platform_query.get_mut(platform_entity) {
+            Ok((_, mut platform_object)) if landed => land_on_platform(
+                &mut player_entity.1,
+                platform_entity,
+                &mut platform_object,
+                *control_mode,
+                &mut floor_reached_event,
+            ),
+            _ => player_entity.1.passing_platforms.push(platform_entity),
+        }
+    }
+}
+
+// Counts the platform as ground underneath the player, and scores it the first time it is landed on
+fn land_on_platform(
+    player: &mut Player,
+    platform_entity: Entity,
+    platform_object: &mut Platform,
+    control_mode: ControlMode,
+    floor_reached_event: &mut EventWriter<FloorReachedEvent>,
+) {
+    player.ground_contacts += 1;
+    player.surface = platform_object.surface;
+    player.ground_platform = Some(platform_entity);
+    // Landing arms the bounce, it fires as soon as the player counts as grounded
+    if control_mode == ControlMode::AutoBounce {
+        player.jump_buffer_timer.reset();
+    }
+    // If the platform has not already been collided with, increase the player's score and set the platform's already collided flag to true
+    if !platform_object.already_collided {
+        player.score += 1;
+        platform_object.already_collided = true;
+        floor_reached_event.send(FloorReachedEvent(platform_entity));
+    }
+}
+
+// The player stays grounded for the coyote time after leaving a platform, instead of the moment the sensor lets go
+fn player_grounded_system(mut player_query: Query<&mut Player>) {
+    let mut player = player_query.single_mut();
+
+    let timestep = Duration::from_secs_f32(FIXED_TIMESTEP);
+    player.coyote_timer.tick(timestep);
+    player.jump_buffer_timer.tick(timestep);
+
+    player.player_grounded = player.ground_contacts > 0 || !player.coyote_timer.finished();
 }
 
 fn player_screen_looping_system(
@@ -244,13 +528,3 @@ fn player_screen_looping_system(
         player_transform.0.translation.x = window_dimensions.width / 2.0 + PLAYER_SIZE / 2.0_f32;
     }
 }
-
-fn player_animation_system(mut player_query: Query<((&mut Sprite, &Player), With<Player>)>) {
-    let (mut player_sprite, _player_object) = player_query.single_mut();
-
-    if player_sprite.1.player_facing_right {
-        player_sprite.0.flip_x = false;
-    } else {
-        player_sprite.0.flip_x = true;
-    }
-}
@@ -0,0 +1,339 @@
+use crate::gameplay_state::{FixedStepAppExt, PlayerRespawnedEvent, FIXED_TIMESTEP};
+use crate::player_input::{PlayerInput, PlayerInputSampling};
+use crate::{Player, PLAYER_SIZE};
+use bevy::asset::{AssetLoader, LoadContext, LoadedAsset};
+use bevy::prelude::*;
+use bevy::reflect::TypeUuid;
+use bevy::sprite::Anchor;
+use bevy::utils::BoxedFuture;
+use bevy_rapier2d::prelude::Velocity;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fmt;
+
+pub struct PlayerAnimationPlugin;
+
+pub const PLAYER_ANIMATIONS_PATH: &str = "player.animations.ron";
+
+// Used when the animation file could not be loaded, the player is then drawn as a single still frame
+const FALLBACK_TEXTURE: &str = "PlayerTexture.png";
+const FALLBACK_TILE_SIZE: Vec2 = Vec2::new(59.0, 53.0);
+
+// Vertical speeds below these count as standing still rather than rising or falling
+const RISING_SPEED: f32 = 10.0;
+const FALLING_SPEED: f32 = 10.0;
+
+// What the player is doing, each state plays its own animation
+#[derive(Component, Deserialize, Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum PlayerState {
+    #[default]
+    Idle,
+    Rising,
+    Falling,
+    FastFalling,
+    Landing,
+    Dead,
+}
+
+// The player's sprite sheet and the animation of every state, loaded from a `.animations.ron` file
+#[derive(Deserialize, TypeUuid, Debug, Clone)]
+#[uuid = "f99dec77-5434-430a-8285-f266d016644b"]
+pub struct PlayerAnimations {
+    // A path relative to the assets folder
+    pub texture: String,
+    pub tile_size: (f32, f32),
+    pub columns: usize,
+    pub rows: usize,
+    pub states: BTreeMap<PlayerState, StateAnimation>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct StateAnimation {
+    pub frames: Vec<AnimationFrame>,
+    #[serde(default)]
+    pub looping: bool,
+    // The states that may take over before the animation has played out, any state may when left out
+    #[serde(default)]
+    pub interrupted_by: Option<Vec<PlayerState>>,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy)]
+pub struct AnimationFrame {
+    // The tile of the sprite sheet, counted left to right and top to bottom
+    pub index: usize,
+    // Seconds the frame is shown for
+    pub duration: f32,
+    // Stretches the sprite from the player's feet, used for squash and stretch
+    #[serde(default = "AnimationFrame::default_scale")]
+    pub scale: (f32, f32),
+}
+
+#[derive(Debug)]
+pub enum PlayerAnimationsError {
+    Ron(ron::error::SpannedError),
+    MissingState(PlayerState),
+    NoFrames(PlayerState),
+    IndexOutOfRange { state: PlayerState, index: usize },
+    InvalidDuration { state: PlayerState, frame: usize },
+}
+
+#[derive(Resource, Default)]
+pub struct PlayerAnimationsHandle(pub Handle<PlayerAnimations>);
+
+// Plays the player's state, it lives on a child of the player so squash and stretch leave the collider alone
+#[derive(Component, Default)]
+pub struct PlayerAnimator {
+    state: PlayerState,
+    frame: usize,
+    frame_time: f32,
+    finished: bool,
+    was_on_ground: bool,
+}
+
+#[derive(Default)]
+pub struct PlayerAnimationsLoader;
+
+impl Plugin for PlayerAnimationPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_asset::<PlayerAnimations>()
+            .init_asset_loader::<PlayerAnimationsLoader>()
+            .init_resource::<PlayerAnimationsHandle>()
+            .add_startup_system(load_player_animations_system)
+            .add_fixed_step_system_set(
+                SystemSet::new()
+                    .with_system(player_state_system.after(PlayerInputSampling))
+                    .with_system(animate_player_system.after(player_state_system)),
+            );
+    }
+}
+
+impl AnimationFrame {
+    fn default_scale() -> (f32, f32) {
+        (1.0, 1.0)
+    }
+}
+
+impl PlayerAnimations {
+    // Parses and validates the animations, so a broken file is reported when it is loaded
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, PlayerAnimationsError> {
+        let animations: PlayerAnimations =
+            ron::de::from_bytes(bytes).map_err(PlayerAnimationsError::Ron)?;
+
+        for state in [
+            PlayerState::Idle,
+            PlayerState::Rising,
+            PlayerState::Falling,
+            PlayerState::FastFalling,
+            PlayerState::Landing,
+            PlayerState::Dead,
+        ] {
+            let Some(animation) = animations.states.get(&state) else {
+                return Err(PlayerAnimationsError::MissingState(state));
+            };
+            if animation.frames.is_empty() {
+                return Err(PlayerAnimationsError::NoFrames(state));
+            }
+            for (frame, animation_frame) in animation.frames.iter().enumerate() {
+                if animation_frame.index >= animations.columns * animations.rows {
+                    return Err(PlayerAnimationsError::IndexOutOfRange {
+                        state,
+                        index: animation_frame.index,
+                    });
+                }
+                if animation_frame.duration <= 0.0 {
+                    return Err(PlayerAnimationsError::InvalidDuration { state, frame });
+                }
+            }
+        }
+
+        Ok(animations)
+    }
+}
+
+impl StateAnimation {
+    fn can_be_interrupted_by(&self, state: PlayerState) -> bool {
+        self.interrupted_by
+            .as_ref()
+            .is_none_or(|states| states.contains(&state))
+    }
+}
+
+impl fmt::Display for PlayerAnimationsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PlayerAnimationsError::Ron(err) => write!(f, "malformed player animations: {}", err),
+            PlayerAnimationsError::MissingState(state) => {
+                write!(f, "there is no animation for the {:?} state", state)
+            }
+            PlayerAnimationsError::NoFrames(state) => {
+                write!(f, "the {:?} animation has no frames", state)
+            }
+            PlayerAnimationsError::IndexOutOfRange { state, index } => write!(
+                f,
+                "the {:?} animation uses tile {} which is outside the sprite sheet",
+                state, index
+            ),
+            PlayerAnimationsError::InvalidDuration { state, frame } => write!(
+                f,
+                "frame {} of the {:?} animation needs a positive duration",
+                frame, state
+            ),
+        }
+    }
+}
+
+impl std::error::Error for PlayerAnimationsError {}
+
+impl AssetLoader for PlayerAnimationsLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), bevy::asset::Error>> {
+        Box::pin(async move {
+            let animations = PlayerAnimations::from_bytes(bytes)?;
+            load_context.set_default_asset(LoadedAsset::new(animations));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["animations.ron"]
+    }
+}
+
+fn load_player_animations_system(
+    mut player_animations_handle: ResMut<PlayerAnimationsHandle>,
+    asset_server: Res<AssetServer>,
+) {
+    player_animations_handle.0 = asset_server.load(PLAYER_ANIMATIONS_PATH);
+}
+
+// Spawns the player's animated sprite, to be added as a child of the player
+pub fn spawn_player_sprite(
+    commands: &mut Commands,
+    asset_server: &AssetServer,
+    texture_atlases: &mut Assets<TextureAtlas>,
+    player_animations: Option<&PlayerAnimations>,
+) -> Entity {
+    let texture_atlas = match player_animations {
+        Some(player_animations) => TextureAtlas::from_grid(
+            asset_server.load(player_animations.texture.as_str()),
+            Vec2::new(player_animations.tile_size.0, player_animations.tile_size.1),
+            player_animations.columns,
+            player_animations.rows,
+            None,
+            None,
+        ),
+        None => TextureAtlas::from_grid(
+            asset_server.load(FALLBACK_TEXTURE),
+            FALLBACK_TILE_SIZE,
+            1,
+            1,
+            None,
+            None,
+        ),
+    };
+
+    commands
+        .spawn((
+            SpriteSheetBundle {
+                sprite: TextureAtlasSprite {
+                    custom_size: Some(Vec2::new(PLAYER_SIZE, PLAYER_SIZE)),
+                    // Scaling from the feet keeps the player standing on the platform while squashed
+                    anchor: Anchor::BottomCenter,
+                    ..default()
+                },
+                texture_atlas: texture_atlases.add(texture_atlas),
+                transform: Transform::from_xyz(0.0, -PLAYER_SIZE / 2.0, 0.0),
+                ..default()
+            },
+            PlayerAnimator::default(),
+        ))
+        .id()
+}
+
+// Works out what the player is doing from their movement, unless the current animation has to play out first
+fn player_state_system(
+    mut player_query: Query<(&Player, &Velocity, &mut PlayerState)>,
+    animator_query: Query<&PlayerAnimator>,
+    mut respawn_events: EventReader<PlayerRespawnedEvent>,
+    player_input: Res<PlayerInput>,
+    player_animations_handle: Res<PlayerAnimationsHandle>,
+    player_animations: Res<Assets<PlayerAnimations>>,
+) {
+    let (player, velocity, mut player_state) = player_query.single_mut();
+    let animator = animator_query.single();
+
+    let landed = player.on_ground() && !animator.was_on_ground;
+    // Played once the player is back at the respawn point, so it is seen instead of happening off screen
+    let next_state = if respawn_events.iter().last().is_some() {
+        PlayerState::Dead
+    } else if landed {
+        PlayerState::Landing
+    } else if player_input.fast_fall && !player.on_ground() {
+        PlayerState::FastFalling
+    } else if velocity.linvel.y > RISING_SPEED {
+        PlayerState::Rising
+    } else if velocity.linvel.y < -FALLING_SPEED {
+        PlayerState::Falling
+    } else {
+        PlayerState::Idle
+    };
+
+    let current_animation = player_animations
+        .get(&player_animations_handle.0)
+        .and_then(|player_animations| player_animations.states.get(&*player_state));
+    let locked = animator.state == *player_state
+        && !animator.finished
+        && current_animation.is_some_and(|animation| !animation.can_be_interrupted_by(next_state));
+
+    if !locked && *player_state != next_state {
+        *player_state = next_state;
+    }
+}
+
+// Steps through the frames of the player's state and applies them to the sprite
+fn animate_player_system(
+    player_query: Query<(&Player, &PlayerState)>,
+    mut animator_query: Query<(&mut PlayerAnimator, &mut TextureAtlasSprite, &mut Transform)>,
+    player_animations_handle: Res<PlayerAnimationsHandle>,
+    player_animations: Res<Assets<PlayerAnimations>>,
+) {
+    let (player, player_state) = player_query.single();
+    let (mut animator, mut sprite, mut sprite_transform) = animator_query.single_mut();
+
+    animator.was_on_ground = player.on_ground();
+    sprite.flip_x = !player.facing_right();
+
+    if animator.state != *player_state {
+        animator.state = *player_state;
+        animator.frame = 0;
+        animator.frame_time = 0.0;
+        animator.finished = false;
+    }
+
+    let Some(animation) = player_animations
+        .get(&player_animations_handle.0)
+        .and_then(|player_animations| player_animations.states.get(player_state))
+    else {
+        return;
+    };
+
+    // Uses the fixed timestep so the animation freezes along with the rest of the run when paused
+    animator.frame_time += FIXED_TIMESTEP;
+    while !animator.finished && animator.frame_time >= animation.frames[animator.frame].duration {
+        animator.frame_time -= animation.frames[animator.frame].duration;
+        if animator.frame + 1 < animation.frames.len() {
+            animator.frame += 1;
+        } else if animation.looping {
+            animator.frame = 0;
+        } else {
+            animator.finished = true;
+        }
+    }
+
+    let frame = animation.frames[animator.frame];
+    sprite.index = frame.index;
+    sprite_transform.scale = Vec3::new(frame.scale.0, frame.scale.1, 1.0);
+}
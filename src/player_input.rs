@@ -0,0 +1,147 @@
+use crate::gameplay_state::FixedStepAppExt;
+use crate::input_bindings::InputAction;
+use crate::GameplayStateSubstates;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+pub struct PlayerInputPlugin;
+
+// The player's input for a single simulation tick, whichever source it came from
+#[derive(Resource, Serialize, Deserialize, Debug, Default, Clone, Copy, PartialEq)]
+pub struct PlayerInput {
+    // From -1.0 (full left) to 1.0 (full right), analog sticks land anywhere in between
+    pub x_axis: f32,
+    // Whether the jump button is held, and whether it went down this tick
+    pub jump: bool,
+    pub jump_pressed: bool,
+    pub fast_fall: bool,
+    pub drop_through: bool,
+    pub restart: bool,
+}
+
+// Where `PlayerInput` is filled in from each tick
+#[derive(Resource, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum InputSource {
+    #[default]
+    Live,
+    // A recorded run is being played back
+    Replay,
+}
+
+// Stick deflection below this is treated as centered, the rest of the range is rescaled to start at zero
+const STICK_DEADZONE: f32 = 0.2;
+
+// Set by the pause menu, the restart goes through the next tick's input so replays pick it up
+#[derive(Resource, Default)]
+pub struct RestartRequested(pub bool);
+
+// Presses seen since the last tick, kept until a tick picks them up so none are lost or doubled
+// when a frame runs no tick or several
+#[derive(Resource, Default)]
+struct PendingPresses {
+    jump: bool,
+    restart: bool,
+}
+
+// Systems that fill in `PlayerInput`, gameplay systems reading it run after this label
+#[derive(SystemLabel)]
+pub struct PlayerInputSampling;
+
+impl Plugin for PlayerInputPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<PlayerInput>()
+            .init_resource::<InputSource>()
+            .init_resource::<RestartRequested>()
+            .init_resource::<PendingPresses>()
+            .add_system(gamepad_connection_system)
+            .add_system_set(
+                SystemSet::on_enter(GameplayStateSubstates::PreGame)
+                    .with_system(clear_pending_presses_system),
+            )
+            .add_system_set(
+                SystemSet::on_update(GameplayStateSubstates::DuringGame)
+                    .with_system(collect_presses_system),
+            )
+            .add_fixed_step_system_set(
+                SystemSet::new().with_system(sample_live_input_system.label(PlayerInputSampling)),
+            );
+    }
+}
+
+// Presses left over from the previous run do not carry into the next one
+fn clear_pending_presses_system(mut pending_presses: ResMut<PendingPresses>) {
+    *pending_presses = PendingPresses::default();
+}
+
+fn collect_presses_system(
+    action_input: Res<Input<InputAction>>,
+    mut pending_presses: ResMut<PendingPresses>,
+) {
+    pending_presses.jump |= action_input.just_pressed(InputAction::Jump);
+    pending_presses.restart |= action_input.just_pressed(InputAction::Restart);
+}
+
+// Logs controllers coming and going, they can be plugged in or out at any time
+fn gamepad_connection_system(mut gamepad_events: EventReader<GamepadEvent>) {
+    for event in gamepad_events.iter() {
+        match &event.event_type {
+            GamepadEventType::Connected(info) => {
+                info!("Gamepad {} connected: {}", event.gamepad.id, info.name);
+            }
+            GamepadEventType::Disconnected => {
+                info!("Gamepad {} disconnected", event.gamepad.id);
+            }
+            _ => {}
+        }
+    }
+}
+
+fn sample_live_input_system(
+    action_input: Res<Input<InputAction>>,
+    gamepads: Res<Gamepads>,
+    gamepad_axes: Res<Axis<GamepadAxis>>,
+    input_source: Res<InputSource>,
+    mut player_input: ResMut<PlayerInput>,
+    mut restart_requested: ResMut<RestartRequested>,
+    mut pending_presses: ResMut<PendingPresses>,
+) {
+    if *input_source != InputSource::Live {
+        return;
+    }
+
+    // Bound keys and buttons always move at full speed
+    let left = action_input.pressed(InputAction::MoveLeft);
+    let right = action_input.pressed(InputAction::MoveRight);
+    let mut x_axis = right as i8 as f32 - left as i8 as f32;
+
+    // Every connected gamepad's stick can steer, whichever input is pushed the furthest wins
+    for gamepad in gamepads.iter() {
+        let stick_x_axis = stick_x_axis(gamepad, &gamepad_axes);
+        if stick_x_axis.abs() > x_axis.abs() {
+            x_axis = stick_x_axis;
+        }
+    }
+
+    *player_input = PlayerInput {
+        x_axis,
+        jump: action_input.pressed(InputAction::Jump),
+        jump_pressed: std::mem::take(&mut pending_presses.jump),
+        fast_fall: action_input.pressed(InputAction::FastFall),
+        drop_through: action_input.pressed(InputAction::DropThrough),
+        restart: std::mem::take(&mut restart_requested.0)
+            | std::mem::take(&mut pending_presses.restart),
+    };
+}
+
+// The left stick moves as fast as it is deflected
+fn stick_x_axis(gamepad: Gamepad, gamepad_axes: &Axis<GamepadAxis>) -> f32 {
+    let stick_x = gamepad_axes
+        .get(GamepadAxis::new(gamepad, GamepadAxisType::LeftStickX))
+        .unwrap_or(0.0);
+    if stick_x.abs() < STICK_DEADZONE {
+        return 0.0;
+    }
+
+    let deflection = (stick_x.abs() - STICK_DEADZONE) / (1.0 - STICK_DEADZONE);
+    deflection.min(1.0).copysign(stick_x)
+}
@@ -0,0 +1,241 @@
+use crate::gameplay_state::{FIXED_TIMESTEP, GRAVITY};
+use crate::platforms::{moving_bounds, PlatformType, Surface, PLATFORM_HEIGHT, PLATFORM_WIDTH};
+use crate::player::{
+    BOUNCY_JUMP_MULTIPLIER, FALL_GRAVITY_SCALE, GROUND_SENSOR_DEPTH, JUMP_FORCE, MOVEMENT_SPEED,
+    PLAYER_RADIUS,
+};
+use crate::tower_layout::FloorLayout;
+use crate::{WindowDimensions, PLAYER_SIZE};
+
+// How the player moves through the air, what the tower generator checks every floor against
+#[derive(Debug, Clone, Copy)]
+pub struct JumpArc {
+    pub jump_force: f32,
+    // Pulls down, in pixels per second squared
+    pub gravity: f32,
+    // Multiplies the gravity on the way down
+    pub fall_gravity_scale: f32,
+    pub movement_speed: f32,
+}
+
+// The horizontal positions the player can be at, one column for every step of movement
+struct Columns {
+    reachable: Vec<bool>,
+    left_edge: f32,
+    width: f32,
+    // Walking off the right edge of the window comes back in a little way past the left one
+    wrap_column: usize,
+}
+
+impl JumpArc {
+    // The player's own arc, coming down with the heavier gravity of the jump button so generated
+    // towers can be climbed with either control mode
+    pub const PLAYER: JumpArc = JumpArc {
+        jump_force: JUMP_FORCE,
+        gravity: -GRAVITY,
+        fall_gravity_scale: FALL_GRAVITY_SCALE,
+        movement_speed: MOVEMENT_SPEED,
+    };
+}
+
+impl Columns {
+    // The player loops from half their size past the right edge to half their size inside the left
+    // one, and from the left edge to half their size past the right one
+    fn new(window: &WindowDimensions, width: f32) -> Self {
+        let span = window.width + PLAYER_SIZE / 2.0;
+        Columns {
+            reachable: vec![false; (span / width).ceil() as usize],
+            left_edge: -window.width / 2.0,
+            width,
+            wrap_column: (PLAYER_SIZE / 2.0 / width).round() as usize,
+        }
+    }
+
+    // The columns within `half_width` of `x`, and always the one `x` falls in
+    fn within(&self, x: f32, half_width: f32) -> impl Iterator<Item = usize> + '_ {
+        (0..self.reachable.len()).filter(move |column| {
+            let centre = self.left_edge + (*column as f32 + 0.5) * self.width;
+            (centre - x).abs() <= half_width + self.width / 2.0
+        })
+    }
+
+    fn set(&mut self, x: f32, half_width: f32, reachable: bool) {
+        for column in self.within(x, half_width).collect::<Vec<_>>() {
+            self.reachable[column] = reachable;
+        }
+    }
+
+    fn any(&self, x: f32, half_width: f32) -> bool {
+        self.within(x, half_width)
+            .any(|column| self.reachable[column])
+    }
+
+    // One more step of movement to either side
+    fn spread(&mut self) {
+        let last = self.reachable.len() - 1;
+        let previous = self.reachable.clone();
+        for column in (0..=last).filter(|column| previous[*column]) {
+            let left = if column == 0 { last } else { column - 1 };
+            let right = if column == last {
+                self.wrap_column
+            } else {
+                column + 1
+            };
+            self.reachable[left] = true;
+            self.reachable[right] = true;
+        }
+    }
+}
+
+// Where the player can stand on a floor, as its middle and half its width. Moving platforms count
+// as anywhere along their track since the player can wait for them, path platforms as where they start
+fn standing_area(window: &WindowDimensions, floor: &FloorLayout) -> (f32, f32) {
+    let width = floor.width.unwrap_or(PLATFORM_WIDTH);
+    match (floor.platform_type, &floor.path) {
+        (PlatformType::Moving, _) => {
+            let (left_bound, right_bound) = moving_bounds(window, floor, width);
+            (
+                (left_bound + right_bound) / 2.0,
+                (right_bound - left_bound + width) / 2.0,
+            )
+        }
+        (PlatformType::Path, Some(path)) if !path.waypoints.is_empty() => {
+            (floor.x + path.waypoints[0].0, width / 2.0)
+        }
+        _ => (floor.x, width / 2.0),
+    }
+}
+
+// Whether the player can bounce off `from` and land on `to`, following the jump arc one fixed step
+// at a time while keeping track of every horizontal position they could be at by then
+pub fn floor_reachable(
+    jump_arc: &JumpArc,
+    window: &WindowDimensions,
+    from: &FloorLayout,
+    to: &FloorLayout,
+) -> bool {
+    let launch_force = match from.surface {
+        Surface::Bouncy => jump_arc.jump_force * BOUNCY_JUMP_MULTIPLIER,
+        _ => jump_arc.jump_force,
+    };
+    let to_top = to.y + PLATFORM_HEIGHT / 2.0;
+    let to_bottom = to.y - PLATFORM_HEIGHT / 2.0;
+
+    let mut columns = Columns::new(window, jump_arc.movement_speed * FIXED_TIMESTEP);
+    let (from_x, from_half_width) = standing_area(window, from);
+    columns.set(from_x, from_half_width, true);
+
+    // A solid platform that stays put stops the player jumping into it from below, a moving one
+    // can be waited out
+    let (to_x, to_half_width) = standing_area(window, to);
+    let blocks_jump = !to.one_way && to.platform_type != PlatformType::Moving;
+
+    // Tracks the bottom of the ground sensor, the bounce starts as soon as it touches the platform
+    let mut feet = from.y + PLATFORM_HEIGHT / 2.0;
+    let mut velocity = launch_force;
+    loop {
+        let gravity = if velocity > 0.0 {
+            jump_arc.gravity
+        } else {
+            jump_arc.gravity * jump_arc.fall_gravity_scale
+        };
+        let previous_feet = feet;
+        let previous_velocity = velocity;
+        velocity -= gravity * FIXED_TIMESTEP;
+        feet += (previous_velocity + velocity) / 2.0 * FIXED_TIMESTEP;
+        columns.spread();
+
+        let centre = feet + GROUND_SENSOR_DEPTH;
+        if blocks_jump
+            && velocity > 0.0
+            && centre + PLAYER_RADIUS > to_bottom
+            && centre - PLAYER_RADIUS < to_top
+        {
+            columns.set(to_x, to_half_width + PLAYER_RADIUS, false);
+        }
+
+        // The player lands on the way down, when the sensor comes back down to the top of the platform
+        if velocity <= 0.0 && feet <= to_top {
+            return previous_feet > to_top && columns.any(to_x, to_half_width);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WINDOW: WindowDimensions = WindowDimensions {
+        width: 960.0,
+        height: 540.0,
+    };
+
+    // The highest the player's feet get above the floor they bounce off, without a bouncy surface
+    fn apex() -> f32 {
+        JumpArc::PLAYER.jump_force.powi(2) / (2.0 * JumpArc::PLAYER.gravity)
+    }
+
+    fn floor(x: f32, y: f32) -> FloorLayout {
+        FloorLayout {
+            x,
+            y,
+            platform_type: PlatformType::Stationary,
+            speed: 0.0,
+            bounds: None,
+            texture: None,
+            crumble_delay: None,
+            surface: Surface::Normal,
+            path: None,
+            one_way: false,
+            width: None,
+            checkpoint: false,
+            coin: None,
+        }
+    }
+
+    fn reachable(from: &FloorLayout, to: &FloorLayout) -> bool {
+        floor_reachable(&JumpArc::PLAYER, &WINDOW, from, to)
+    }
+
+    #[test]
+    fn floor_within_apex_is_reachable() {
+        // Off to the side, so the floor is not in the way on the way up
+        let to = floor(PLATFORM_WIDTH * 1.5, apex() * 0.75);
+        assert!(reachable(&floor(0.0, 0.0), &to));
+    }
+
+    #[test]
+    fn floor_above_apex_is_not_reachable() {
+        let to = floor(PLATFORM_WIDTH * 1.5, apex() + PLATFORM_HEIGHT);
+        assert!(!reachable(&floor(0.0, 0.0), &to));
+    }
+
+    #[test]
+    fn solid_floor_overhead_blocks_the_jump() {
+        let from = floor(0.0, 0.0);
+        let mut to = floor(0.0, apex() * 0.75);
+        assert!(!reachable(&from, &to));
+
+        to.one_way = true;
+        assert!(reachable(&from, &to));
+    }
+
+    #[test]
+    fn floor_is_reachable_across_the_screen_wrap() {
+        // Too far apart to reach across the middle of the window
+        let half_span = WINDOW.width / 2.0 - PLATFORM_WIDTH / 2.0;
+        let from = floor(half_span, 0.0);
+        let to = floor(-half_span, apex() * 0.75);
+        assert!(reachable(&from, &to));
+    }
+
+    #[test]
+    fn bouncy_floor_reaches_higher() {
+        let mut from = floor(0.0, 0.0);
+        let to = floor(PLATFORM_WIDTH * 1.5, apex() * 1.5);
+        assert!(!reachable(&from, &to));
+
+        from.surface = Surface::Bouncy;
+        assert!(reachable(&from, &to));
+    }
+}
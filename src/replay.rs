@@ -0,0 +1,383 @@
+use crate::difficulty::Difficulty;
+use crate::gameplay_state::{FixedStepAppExt, FIXED_TIMESTEP};
+use crate::hazard::HazardEnabled;
+use crate::platforms::LevelSeed;
+use crate::player::ControlMode;
+use crate::player_input::{InputSource, PlayerInput, PlayerInputSampling};
+use crate::save_data::{data_directory, write_file_atomically, SaveData};
+use crate::{command_line_value, GameMode, GameplayStateSubstates, Player};
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+pub struct ReplayPlugin;
+
+// Bumped whenever the layout of `Replay` or the towers built from a seed change, older replays are not played back
+pub const REPLAY_VERSION: u32 = 1;
+
+const REPLAY_DIRECTORY_NAME: &str = "replays";
+const LAST_REPLAY_FILE_NAME: &str = "last.replay.ron";
+// The best replays of this many of the latest seeds are kept, along with those of every seed on a leaderboard
+const KEPT_BEST_REPLAY_SEEDS: usize = 20;
+
+// How far the player may drift from the recorded position before the playback is reported as out of sync
+const DESYNC_TOLERANCE: f32 = 1.0;
+
+// A single simulation tick of a recorded run
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+pub struct ReplayFrame {
+    pub input: PlayerInput,
+    // Where the player was at the start of the tick, used to catch playback drifting from the recording
+    pub position: (f32, f32),
+}
+
+// A run recorded as the level seed plus the input of every tick
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Replay {
+    pub version: u32,
+    pub seed: u64,
+    pub mode: GameMode,
+    pub difficulty: Difficulty,
+    pub control_mode: ControlMode,
+    // Whether the rising hazard was chasing the player
+    pub hazard: bool,
+    // The floors reached, endless runs are compared by it
+    pub score: u32,
+    // The length of a tick the run was recorded with
+    pub timestep: f32,
+    pub frames: Vec<ReplayFrame>,
+}
+
+// The frames of the run in progress
+#[derive(Resource, Default)]
+struct ReplayRecorder(Vec<ReplayFrame>);
+
+// The replay given with `--replay`, it drives the player instead of the keyboard
+#[derive(Resource)]
+struct ReplayPlayback {
+    replay: Replay,
+    frame: usize,
+    desynced: bool,
+}
+
+impl Plugin for ReplayPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ReplayRecorder>()
+            .add_system_set(
+                SystemSet::on_enter(GameplayStateSubstates::PreGame)
+                    .with_system(start_recording_system)
+                    .with_system(start_playback_system),
+            )
+            .add_fixed_step_system_set(
+                SystemSet::new()
+                    .with_system(play_back_replay_system.label(PlayerInputSampling))
+                    .with_system(record_replay_frame_system.after(PlayerInputSampling)),
+            )
+            .add_system_set(
+                SystemSet::on_enter(GameplayStateSubstates::PostGame)
+                    .with_system(save_replay_system),
+            );
+
+        if let Some(replay) = command_line_value("--replay").and_then(Replay::load) {
+            if replay.timestep != FIXED_TIMESTEP {
+                warn!("The replay was recorded with a different timestep and may desync");
+            }
+            if replay.mode == GameMode::CustomTower {
+                warn!("The replay was recorded on a custom tower, it needs the same --level file");
+            }
+            info!("Playing back a replay of {} frames", replay.frames.len());
+
+            app.insert_resource(ReplayPlayback {
+                replay,
+                frame: 0,
+                desynced: false,
+            })
+            .insert_resource(InputSource::Replay);
+        }
+    }
+}
+
+impl Replay {
+    fn load(path: impl AsRef<Path>) -> Option<Self> {
+        let path = path.as_ref();
+
+        let contents = match fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(err) => {
+                error!("Could not read replay {}: {}", path.display(), err);
+                return None;
+            }
+        };
+
+        let replay = match ron::from_str::<Replay>(&contents) {
+            Ok(replay) => replay,
+            Err(err) => {
+                error!("Replay {} is corrupted: {}", path.display(), err);
+                return None;
+            }
+        };
+
+        if replay.version != REPLAY_VERSION {
+            error!(
+                "Replay {} is from an unsupported version ({})",
+                path.display(),
+                replay.version
+            );
+            return None;
+        }
+
+        Some(replay)
+    }
+
+    // The best run recorded on the tower the next run is built from, if there is one
+    pub fn load_best(
+        level_seed: LevelSeed,
+        game_mode: GameMode,
+        difficulty: Difficulty,
+    ) -> Option<Self> {
+        let path = best_replay_path(level_seed, game_mode, difficulty)?;
+        path.exists().then(|| Self::load(&path)).flatten()
+    }
+
+    // Every frame is one fixed tick of the run, so fewer frames is a faster run
+    // An endless run is better when it climbed higher, and only faster when it climbed as high
+    fn is_better_than(&self, other: &Replay) -> bool {
+        match self.mode {
+            GameMode::Endless if self.score != other.score => self.score > other.score,
+            _ => self.frames.len() < other.frames.len(),
+        }
+    }
+
+    fn save(&self, path: &Path) {
+        let contents = match ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default()) {
+            Ok(contents) => contents,
+            Err(err) => {
+                error!("Could not serialize replay: {}", err);
+                return;
+            }
+        };
+
+        match write_file_atomically(path, &contents) {
+            Ok(()) => info!("Saved replay to {}", path.display()),
+            Err(err) => error!("Could not write replay {}: {}", path.display(), err),
+        }
+    }
+}
+
+// The replay of the latest completed run, overwritten by every new one
+fn last_replay_path() -> Option<PathBuf> {
+    data_directory().map(|directory| {
+        directory
+            .join(REPLAY_DIRECTORY_NAME)
+            .join(LAST_REPLAY_FILE_NAME)
+    })
+}
+
+// The replay of the fastest run on a tower, generated towers are told apart by their seed and difficulty
+fn best_replay_path(
+    level_seed: LevelSeed,
+    game_mode: GameMode,
+    difficulty: Difficulty,
+) -> Option<PathBuf> {
+    let file_name = match game_mode {
+        GameMode::Standard => format!(
+            "best-{}-{}.replay.ron",
+            difficulty.label().to_lowercase(),
+            level_seed.0
+        ),
+        GameMode::Endless => format!(
+            "best-endless-{}-{}.replay.ron",
+            difficulty.label().to_lowercase(),
+            level_seed.0
+        ),
+        // The seed already picks the difficulty of a daily challenge
+        GameMode::Daily => format!("best-daily-{}.replay.ron", level_seed.0),
+        // Custom towers ignore the seed, so they are told apart by their layout file instead
+        GameMode::CustomTower => {
+            let level: String = command_line_value("--level")
+                .unwrap_or_default()
+                .chars()
+                .map(|character| {
+                    if character.is_ascii_alphanumeric() {
+                        character
+                    } else {
+                        '_'
+                    }
+                })
+                .collect();
+            format!("best-custom-{}.replay.ron", level)
+        }
+    };
+
+    data_directory().map(|directory| directory.join(REPLAY_DIRECTORY_NAME).join(file_name))
+}
+
+// The seed a best replay of a generated tower was recorded on, read back from its file name
+fn best_replay_seed(file_name: &str) -> Option<u64> {
+    let name = file_name
+        .strip_prefix("best-")?
+        .strip_suffix(".replay.ron")?;
+    if name.starts_with("custom-") {
+        return None;
+    }
+    name.rsplit('-').next()?.parse().ok()
+}
+
+// Deletes the best replays of seeds that were neither among the latest ones nor made a leaderboard,
+// otherwise every new seed played would leave another file behind
+fn prune_best_replays(directory: &Path, save_data: &SaveData) {
+    let entries = match fs::read_dir(directory) {
+        Ok(entries) => entries,
+        Err(err) => {
+            error!("Could not read replays in {}: {}", directory.display(), err);
+            return;
+        }
+    };
+
+    let mut best_replays: Vec<(SystemTime, u64, PathBuf)> = entries
+        .filter_map(Result::ok)
+        .filter_map(|entry| {
+            let seed = best_replay_seed(entry.file_name().to_str()?)?;
+            let modified = entry.metadata().and_then(|metadata| metadata.modified());
+            Some((modified.ok()?, seed, entry.path()))
+        })
+        .collect();
+    // Latest first
+    best_replays.sort_by_key(|(modified, _, _)| std::cmp::Reverse(*modified));
+
+    let leaderboard_seeds = save_data.leaderboard_seeds();
+    let mut latest_seeds = HashSet::new();
+    for (_, seed, path) in best_replays {
+        if leaderboard_seeds.contains(&seed) || latest_seeds.contains(&seed) {
+            continue;
+        }
+        if latest_seeds.len() < KEPT_BEST_REPLAY_SEEDS {
+            latest_seeds.insert(seed);
+            continue;
+        }
+
+        match fs::remove_file(&path) {
+            Ok(()) => info!("Removed old replay {}", path.display()),
+            Err(err) => error!("Could not remove replay {}: {}", path.display(), err),
+        }
+    }
+}
+
+fn start_recording_system(mut replay_recorder: ResMut<ReplayRecorder>) {
+    replay_recorder.0.clear();
+}
+
+// Every run starts the replay over, on the tower it was recorded on
+fn start_playback_system(
+    replay_playback: Option<ResMut<ReplayPlayback>>,
+    mut level_seed: ResMut<LevelSeed>,
+    mut game_mode: ResMut<GameMode>,
+    mut difficulty: ResMut<Difficulty>,
+    mut control_mode: ResMut<ControlMode>,
+    mut hazard_enabled: ResMut<HazardEnabled>,
+) {
+    let Some(mut replay_playback) = replay_playback else {
+        return;
+    };
+
+    replay_playback.frame = 0;
+    replay_playback.desynced = false;
+    *level_seed = LevelSeed(replay_playback.replay.seed);
+    *game_mode = replay_playback.replay.mode;
+    *difficulty = replay_playback.replay.difficulty;
+    *control_mode = replay_playback.replay.control_mode;
+    hazard_enabled.0 = replay_playback.replay.hazard;
+}
+
+fn play_back_replay_system(
+    replay_playback: Option<ResMut<ReplayPlayback>>,
+    mut player_input: ResMut<PlayerInput>,
+    player_query: Query<&Transform, With<Player>>,
+) {
+    let Some(mut replay_playback) = replay_playback else {
+        return;
+    };
+
+    // The player is left without input once the recording runs out
+    let Some(frame) = replay_playback
+        .replay
+        .frames
+        .get(replay_playback.frame)
+        .copied()
+    else {
+        *player_input = PlayerInput::default();
+        return;
+    };
+    *player_input = frame.input;
+
+    let position = player_query.single().translation.truncate();
+    if !replay_playback.desynced && position.distance(Vec2::from(frame.position)) > DESYNC_TOLERANCE
+    {
+        warn!(
+            "Replay went out of sync on frame {}, expected the player at {:?} but found {:?}",
+            replay_playback.frame, frame.position, position
+        );
+        replay_playback.desynced = true;
+    }
+
+    replay_playback.frame += 1;
+}
+
+fn record_replay_frame_system(
+    mut replay_recorder: ResMut<ReplayRecorder>,
+    player_input: Res<PlayerInput>,
+    player_query: Query<&Transform, With<Player>>,
+) {
+    let position = player_query.single().translation;
+    replay_recorder.0.push(ReplayFrame {
+        input: *player_input,
+        position: (position.x, position.y),
+    });
+}
+
+#[allow(clippy::too_many_arguments)]
+fn save_replay_system(
+    mut replay_recorder: ResMut<ReplayRecorder>,
+    player_query: Query<&Player>,
+    input_source: Res<InputSource>,
+    level_seed: Res<LevelSeed>,
+    game_mode: Res<GameMode>,
+    difficulty: Res<Difficulty>,
+    control_mode: Res<ControlMode>,
+    hazard_enabled: Res<HazardEnabled>,
+    save_data: Res<SaveData>,
+) {
+    // A played back run would only write the same replay again
+    if *input_source == InputSource::Replay {
+        return;
+    }
+
+    let replay = Replay {
+        version: REPLAY_VERSION,
+        seed: level_seed.0,
+        mode: *game_mode,
+        difficulty: *difficulty,
+        control_mode: *control_mode,
+        hazard: hazard_enabled.0,
+        score: player_query.single().score,
+        timestep: FIXED_TIMESTEP,
+        frames: std::mem::take(&mut replay_recorder.0),
+    };
+
+    if let Some(path) = last_replay_path() {
+        replay.save(&path);
+    }
+
+    // The best run on this tower is kept as well, it is raced against as the ghost
+    let better = Replay::load_best(*level_seed, *game_mode, *difficulty)
+        .is_none_or(|best_replay| replay.is_better_than(&best_replay));
+    if let Some(path) = best_replay_path(*level_seed, *game_mode, *difficulty).filter(|_| better) {
+        replay.save(&path);
+        if let Some(directory) = path.parent() {
+            prune_best_replays(directory, &save_data);
+        }
+    }
+}
@@ -0,0 +1,224 @@
+use crate::daily::{DailyAttempt, DailyChallenge};
+use crate::difficulty::Difficulty;
+use crate::game_timer::{FinalGameTime, RunSplits};
+use crate::platforms::LevelSeed;
+use crate::player_input::InputSource;
+use crate::save_data::{RunRecord, RunTower, SaveData};
+use crate::scoring::{self, RunScore};
+use crate::ui::{spawn_menu_button, spawn_menu_root, spawn_menu_text, spawn_menu_title};
+use crate::{AppState, GameMode, Player};
+use bevy::prelude::*;
+
+pub struct ResultsScreenPlugin;
+
+#[derive(Component)]
+struct ResultsScreen;
+
+#[derive(Component, Clone, Copy)]
+enum ResultsButton {
+    Retry,
+    MainMenu,
+}
+
+// A summary of the run that just finished
+#[derive(Resource, Default)]
+pub struct LastRunResult {
+    pub time: f32,
+    pub floors: u32,
+    pub coins: u32,
+    // Only a finished tower earns one, an endless run has no top to finish at
+    pub time_bonus: u32,
+    // The floor and coin points plus the time bonus
+    pub points: u32,
+    // `None` when the tower came from a layout file instead of the seed
+    pub seed: Option<u64>,
+    pub personal_best: bool,
+    // The run's place on the local leaderboard, if it made it on
+    pub leaderboard_place: Option<usize>,
+    // A daily challenge run after the day's scored one, it is not recorded
+    pub practice: bool,
+}
+
+impl Plugin for ResultsScreenPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<LastRunResult>()
+            .add_system_set(
+                SystemSet::on_enter(AppState::Results)
+                    .with_system(record_run_result_system)
+                    .with_system(spawn_results_screen_system.after(record_run_result_system)),
+            )
+            .add_system_set(
+                SystemSet::on_update(AppState::Results).with_system(results_button_system),
+            )
+            .add_system_set(
+                SystemSet::on_exit(AppState::Results).with_system(despawn_results_screen_system),
+            );
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn record_run_result_system(
+    mut last_run_result: ResMut<LastRunResult>,
+    mut save_data: ResMut<SaveData>,
+    player_query: Query<&Player>,
+    final_game_time: Res<FinalGameTime>,
+    run_splits: Res<RunSplits>,
+    level_seed: Res<LevelSeed>,
+    game_mode: Res<GameMode>,
+    difficulty: Res<Difficulty>,
+    input_source: Res<InputSource>,
+    daily_attempt: Res<DailyAttempt>,
+    run_score: Res<RunScore>,
+) {
+    let time = final_game_time.0;
+    let floors = player_query.single().score;
+    let coins = run_score.coins;
+    let time_bonus = match *game_mode {
+        GameMode::Endless => 0,
+        _ => scoring::time_bonus(time, floors),
+    };
+    let points = run_score.points() + time_bonus;
+    let seed = (*game_mode != GameMode::CustomTower).then_some(level_seed.0);
+    let difficulty = difficulty.of_run(*game_mode);
+
+    // A played back run was already recorded when it was first played
+    if *input_source == InputSource::Replay {
+        *last_run_result = LastRunResult {
+            time,
+            floors,
+            coins,
+            time_bonus,
+            points,
+            seed,
+            ..default()
+        };
+        return;
+    }
+
+    // Only the day's first daily challenge run goes on the leaderboard
+    if *game_mode == GameMode::Daily && *daily_attempt == DailyAttempt::Practice {
+        *last_run_result = LastRunResult {
+            time,
+            floors,
+            coins,
+            time_bonus,
+            points,
+            seed,
+            practice: true,
+            ..default()
+        };
+        return;
+    }
+
+    let record = RunRecord::new(
+        time,
+        floors,
+        seed,
+        *game_mode,
+        difficulty,
+        run_splits.0.clone(),
+    );
+    // Compare against the stored best before this run is added to the leaderboard
+    let personal_best = save_data.is_personal_best(&record);
+    let leaderboard_place = save_data.record_run(record, RunTower::of_run(*game_mode, &level_seed));
+
+    *last_run_result = LastRunResult {
+        time,
+        floors,
+        coins,
+        time_bonus,
+        points,
+        seed,
+        personal_best,
+        leaderboard_place,
+        practice: false,
+    };
+}
+
+fn spawn_results_screen_system(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    last_run_result: Res<LastRunResult>,
+    game_mode: Res<GameMode>,
+    daily_challenge: Res<DailyChallenge>,
+) {
+    let font = asset_server.load("papercut.ttf");
+
+    let root = spawn_menu_root(&mut commands, ResultsScreen);
+    commands.entity(root).with_children(|parent| {
+        // Endless runs only finish when the player falls
+        let title = match *game_mode {
+            GameMode::Endless => "RUN OVER",
+            _ => "TOP FLOOR REACHED",
+        };
+        spawn_menu_title(parent, &font, title);
+        spawn_menu_text(parent, &font, &format!("TIME {:.2}", last_run_result.time));
+        spawn_menu_text(
+            parent,
+            &font,
+            &format!(
+                "FLOORS {}  COINS {}",
+                last_run_result.floors, last_run_result.coins
+            ),
+        );
+        spawn_menu_text(
+            parent,
+            &font,
+            &format!(
+                "SCORE {}  TIME BONUS {}",
+                last_run_result.points, last_run_result.time_bonus
+            ),
+        );
+        spawn_menu_text(
+            parent,
+            &font,
+            &match last_run_result.seed {
+                // A played back daily run may be from another day, it shows its seed instead
+                Some(seed) if *game_mode == GameMode::Daily && seed == daily_challenge.seed.0 => {
+                    format!("DAILY {}", daily_challenge.date)
+                }
+                Some(seed) => format!("SEED {}", seed),
+                None => "CUSTOM TOWER".to_string(),
+            },
+        );
+        if last_run_result.practice {
+            spawn_menu_text(parent, &font, "PRACTICE RUN, NOT RECORDED");
+        } else if last_run_result.personal_best {
+            spawn_menu_text(parent, &font, "NEW PERSONAL BEST");
+        } else if let Some(place) = last_run_result.leaderboard_place {
+            spawn_menu_text(parent, &font, &format!("LEADERBOARD #{}", place + 1));
+        }
+        spawn_menu_button(parent, &font, "RETRY", ResultsButton::Retry);
+        spawn_menu_button(parent, &font, "MAIN MENU", ResultsButton::MainMenu);
+    });
+}
+
+fn results_button_system(
+    button_query: Query<(&Interaction, &ResultsButton), Changed<Interaction>>,
+    mut app_state: ResMut<State<AppState>>,
+) {
+    for (interaction, button) in button_query.iter() {
+        if *interaction != Interaction::Clicked {
+            continue;
+        }
+
+        match button {
+            // Retrying keeps the level seed, so the same tower is built again
+            ResultsButton::Retry => {
+                let _ = app_state.set(AppState::Gameplay);
+            }
+            ResultsButton::MainMenu => {
+                let _ = app_state.set(AppState::MainMenu);
+            }
+        }
+    }
+}
+
+fn despawn_results_screen_system(
+    mut commands: Commands,
+    results_screen_query: Query<Entity, With<ResultsScreen>>,
+) {
+    for entities in results_screen_query.iter() {
+        commands.entity(entities).despawn_recursive();
+    }
+}
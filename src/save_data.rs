@@ -0,0 +1,357 @@
+use crate::daily::DailyDate;
+use crate::difficulty::Difficulty;
+use crate::platforms::{LevelSeed, TOWER_VERSION};
+use crate::{command_line_value, GameMode};
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::io::{self, ErrorKind};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+pub struct SaveDataPlugin;
+
+// Bumped whenever the layout of `SaveFile` changes
+pub const SAVE_VERSION: u32 = 1;
+pub const LEADERBOARD_SIZE: usize = 10;
+
+const SAVE_DIRECTORY_NAME: &str = "FloorFifty";
+const SAVE_FILE_NAME: &str = "save.ron";
+
+// A completed run as it is stored in the leaderboard
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct RunRecord {
+    pub time: f32,
+    pub score: u32,
+    // `None` when the tower came from a layout file instead of a seed
+    pub seed: Option<u64>,
+    // Seconds since the unix epoch
+    pub date: u64,
+    pub mode: GameMode,
+    // The run time at which each floor was first landed on, keyed by the floor's place in the tower
+    pub splits: BTreeMap<usize, f32>,
+    // `None` when the tower came from a layout file
+    pub difficulty: Option<Difficulty>,
+    // The `TOWER_VERSION` the seed was played on
+    pub tower_version: u32,
+}
+
+// The tower a run was played on, splits are only compared between runs of the same tower
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub enum RunTower {
+    Seed(u64),
+    // The layout file a custom tower was loaded from
+    Layout(String),
+}
+
+// Runs are only ranked against runs of the same mode on the same difficulty
+pub type RunCategory = (GameMode, Option<Difficulty>);
+
+#[derive(Serialize, Deserialize, Default)]
+struct SaveFile {
+    version: u32,
+    leaderboards: BTreeMap<RunCategory, Vec<RunRecord>>,
+    // The best run on each tower, its splits are what later runs on the tower are compared against
+    tower_bests: BTreeMap<(RunCategory, RunTower), RunRecord>,
+    // The last day the scored daily challenge run was started
+    last_daily_attempt: Option<DailyDate>,
+}
+
+// Only the version is read first, so files written by other versions can be told apart from corrupted ones
+#[derive(Deserialize)]
+struct SaveFileHeader {
+    version: u32,
+}
+
+#[derive(Resource)]
+pub struct SaveData {
+    file: SaveFile,
+    // `None` when there is no platform data directory, runs are then only kept for the session
+    path: Option<PathBuf>,
+}
+
+impl Plugin for SaveDataPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(SaveData::load());
+    }
+}
+
+impl RunRecord {
+    pub fn new(
+        time: f32,
+        score: u32,
+        seed: Option<u64>,
+        mode: GameMode,
+        difficulty: Option<Difficulty>,
+        splits: BTreeMap<usize, f32>,
+    ) -> Self {
+        let date = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |duration| duration.as_secs());
+
+        RunRecord {
+            time,
+            score,
+            seed,
+            date,
+            mode,
+            splits,
+            difficulty,
+            tower_version: TOWER_VERSION,
+        }
+    }
+
+    // Whether the run's seed builds a different tower now than the one it was played on
+    pub fn seed_outdated(&self) -> bool {
+        self.seed.is_some() && self.tower_version != TOWER_VERSION
+    }
+
+    // Endless runs are ranked by how high they got, every other run by how fast it reached the top
+    pub fn ranks_above(&self, other: &RunRecord) -> bool {
+        match self.mode {
+            GameMode::Endless => {
+                self.score > other.score || (self.score == other.score && self.time < other.time)
+            }
+            _ => self.time < other.time,
+        }
+    }
+}
+
+impl RunTower {
+    pub fn of_run(game_mode: GameMode, level_seed: &LevelSeed) -> Self {
+        match game_mode {
+            // Custom towers ignore the seed
+            GameMode::CustomTower => {
+                RunTower::Layout(command_line_value("--level").unwrap_or_default())
+            }
+            _ => RunTower::Seed(level_seed.0),
+        }
+    }
+}
+
+impl SaveData {
+    fn load() -> Self {
+        let path = data_directory().map(|directory| directory.join(SAVE_FILE_NAME));
+
+        let Some(path) = path else {
+            warn!("No data directory found, runs will not be saved");
+            return SaveData {
+                file: SaveFile::new(),
+                path: None,
+            };
+        };
+
+        let contents = match fs::read_to_string(&path) {
+            Ok(contents) => contents,
+            Err(err) if err.kind() == ErrorKind::NotFound => {
+                return SaveData {
+                    file: SaveFile::new(),
+                    path: Some(path),
+                };
+            }
+            Err(err) => {
+                error!("Could not read save file {}: {}", path.display(), err);
+                return SaveData {
+                    file: SaveFile::new(),
+                    path: None,
+                };
+            }
+        };
+
+        match ron::from_str::<SaveFileHeader>(&contents) {
+            // Never overwrite a file written by a newer version of the game
+            Ok(header) if header.version > SAVE_VERSION => {
+                warn!(
+                    "Save file {} is from a newer version ({}), runs will not be saved",
+                    path.display(),
+                    header.version
+                );
+                SaveData {
+                    file: SaveFile::new(),
+                    path: None,
+                }
+            }
+            Ok(_) => match ron::from_str::<SaveFile>(&contents) {
+                Ok(file) => SaveData {
+                    file,
+                    path: Some(path),
+                },
+                Err(err) => {
+                    error!("Save file {} is corrupted: {}", path.display(), err);
+                    Self::start_over(path, "corrupt")
+                }
+            },
+            Err(err) => {
+                error!("Save file {} is corrupted: {}", path.display(), err);
+                Self::start_over(path, "corrupt")
+            }
+        }
+    }
+
+    // Moves an unreadable save file aside so it is not lost, then starts with an empty one
+    fn start_over(path: PathBuf, backup_suffix: &str) -> Self {
+        let backup_path = path.with_extension(format!("ron.{}", backup_suffix));
+        match fs::rename(&path, &backup_path) {
+            Ok(()) => warn!("Moved the old save file to {}", backup_path.display()),
+            Err(err) => error!("Could not back up save file {}: {}", path.display(), err),
+        }
+
+        SaveData {
+            file: SaveFile::new(),
+            path: Some(path),
+        }
+    }
+
+    fn save(&self) {
+        let Some(path) = &self.path else {
+            return;
+        };
+
+        let contents =
+            match ron::ser::to_string_pretty(&self.file, ron::ser::PrettyConfig::default()) {
+                Ok(contents) => contents,
+                Err(err) => {
+                    error!("Could not serialize save file: {}", err);
+                    return;
+                }
+            };
+
+        if let Err(err) = write_file_atomically(path, &contents) {
+            error!("Could not write save file {}: {}", path.display(), err);
+        }
+    }
+
+    // The best runs of a mode on a difficulty, best first
+    pub fn leaderboard(&self, category: RunCategory) -> &[RunRecord] {
+        self.file
+            .leaderboards
+            .get(&category)
+            .map_or(&[], |records| records.as_slice())
+    }
+
+    // Whether a run beats every run already on its category's leaderboard
+    pub fn is_personal_best(&self, record: &RunRecord) -> bool {
+        self.leaderboard((record.mode, record.difficulty))
+            .first()
+            .is_none_or(|best| record.ranks_above(best))
+    }
+
+    // The seed of every generated tower with a run on a leaderboard, left out when the seed has been outdated
+    pub fn leaderboard_seeds(&self) -> HashSet<u64> {
+        self.file
+            .leaderboards
+            .values()
+            .flatten()
+            .filter(|record| !record.seed_outdated())
+            .filter_map(|record| record.seed)
+            .collect()
+    }
+
+    // Whether the scored run of a day's daily challenge has already been started
+    pub fn daily_attempted(&self, date: DailyDate) -> bool {
+        self.file.last_daily_attempt == Some(date)
+    }
+
+    // Uses up the scored run of a day, the save file is written straight away so quitting does not give it back
+    pub fn record_daily_attempt(&mut self, date: DailyDate) {
+        self.file.last_daily_attempt = Some(date);
+        self.save();
+    }
+
+    // The time the best run of a category on a tower first landed on a floor, `None` if it never did or its seed has been outdated since
+    pub fn best_split(&self, category: RunCategory, tower: &RunTower, floor: usize) -> Option<f32> {
+        self.file
+            .tower_bests
+            .get(&(category, tower.clone()))
+            .filter(|record| !record.seed_outdated())
+            .and_then(|record| record.splits.get(&floor).copied())
+    }
+
+    // Adds a run to its category's leaderboard and keeps it as its tower's best if it beat it, then writes the save file
+    // Returns the run's place on the leaderboard, or `None` if it did not make the cut
+    pub fn record_run(&mut self, record: RunRecord, tower: RunTower) -> Option<usize> {
+        let category = (record.mode, record.difficulty);
+        let tower_best = self.file.tower_bests.get(&(category, tower.clone()));
+        if tower_best.is_none_or(|best| best.seed_outdated() || record.ranks_above(best)) {
+            self.file
+                .tower_bests
+                .insert((category, tower), record.clone());
+        }
+
+        let leaderboard = self.file.leaderboards.entry(category).or_default();
+        let place = leaderboard
+            .iter()
+            .position(|other| record.ranks_above(other))
+            .unwrap_or(leaderboard.len());
+        let place = (place < LEADERBOARD_SIZE).then_some(place);
+
+        if let Some(place) = place {
+            leaderboard.insert(place, record);
+            leaderboard.truncate(LEADERBOARD_SIZE);
+        }
+        self.save();
+
+        place
+    }
+}
+
+// The game's folder in the platform data directory, `None` if the platform has none
+pub fn data_directory() -> Option<PathBuf> {
+    dirs::data_dir().map(|directory| directory.join(SAVE_DIRECTORY_NAME))
+}
+
+// Writes to a temporary file first so a crash mid-write cannot corrupt the existing file
+pub fn write_file_atomically(path: &Path, contents: &str) -> io::Result<()> {
+    let mut temporary_path = path.as_os_str().to_owned();
+    temporary_path.push(".tmp");
+
+    path.parent()
+        .map_or(Ok(()), fs::create_dir_all)
+        .and_then(|()| fs::write(&temporary_path, contents))
+        .and_then(|()| fs::rename(&temporary_path, path))
+}
+
+impl SaveFile {
+    fn new() -> Self {
+        SaveFile {
+            version: SAVE_VERSION,
+            ..default()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(seed: Option<u64>, tower_version: u32) -> RunRecord {
+        RunRecord {
+            tower_version,
+            ..RunRecord::new(
+                42.0,
+                0,
+                seed,
+                GameMode::Standard,
+                Some(Difficulty::Normal),
+                BTreeMap::new(),
+            )
+        }
+    }
+
+    #[test]
+    fn runs_on_the_current_tower_version_are_not_outdated() {
+        assert!(!record(Some(7), TOWER_VERSION).seed_outdated());
+    }
+
+    #[test]
+    fn runs_on_an_older_tower_version_are_outdated() {
+        assert!(record(Some(7), TOWER_VERSION - 1).seed_outdated());
+    }
+
+    // A layout file is played as it is, whatever the generator does with seeds
+    #[test]
+    fn runs_without_a_seed_are_never_outdated() {
+        assert!(!record(None, TOWER_VERSION - 1).seed_outdated());
+    }
+}
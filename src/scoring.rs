@@ -0,0 +1,167 @@
+use crate::coins::{Coin, CoinCollectedEvent};
+use crate::gameplay_state::{FixedStepAppExt, FloorReachedEvent, PlayerRespawnedEvent};
+use crate::{GameplayCleanup, GameplayStateSubstates, Platform};
+use bevy::prelude::*;
+
+pub struct ScoringPlugin;
+
+const FLOOR_POINTS: u32 = 100;
+const COIN_POINTS: u32 = 250;
+// A finished tower earns a bonus for every second it was climbed under this many seconds a floor
+const PAR_SECONDS_PER_FLOOR: f32 = 2.5;
+const TIME_BONUS_PER_SECOND: f32 = 20.0;
+
+// The points of the run in progress, the time bonus is only added once the top floor is reached
+#[derive(Resource, Default)]
+pub struct RunScore {
+    // Every floor landed on so far, with its height and the points it was worth
+    floors: Vec<(Entity, f32, u32)>,
+    pub coins: u32,
+    // The multiplier of the latest new floor, one more than the floors jumped past to reach it
+    pub multiplier: u32,
+}
+
+#[derive(Component)]
+struct PointsUI;
+
+impl Plugin for ScoringPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<RunScore>()
+            .add_system_set(
+                SystemSet::on_enter(GameplayStateSubstates::PreGame)
+                    .with_system(reset_run_score_system)
+                    .with_system(spawn_points_ui_system),
+            )
+            .add_fixed_step_system_set(SystemSet::new().with_system(run_score_system))
+            .add_system_set(
+                SystemSet::on_update(GameplayStateSubstates::DuringGame)
+                    .with_system(update_points_ui_system),
+            );
+    }
+}
+
+impl RunScore {
+    pub fn floor_points(&self) -> u32 {
+        self.floors.iter().map(|(_, _, points)| points).sum()
+    }
+
+    pub fn points(&self) -> u32 {
+        self.floor_points() + self.coins * COIN_POINTS
+    }
+
+    fn highest_floor(&self) -> Option<f32> {
+        self.floors
+            .iter()
+            .map(|(_, height, _)| *height)
+            .max_by(|first, second| first.total_cmp(second))
+    }
+}
+
+// The bonus for climbing a tower of `floors` floors in `time` seconds, nothing once it took longer than par
+pub fn time_bonus(time: f32, floors: u32) -> u32 {
+    let seconds_under_par = floors as f32 * PAR_SECONDS_PER_FLOOR - time;
+    (seconds_under_par.max(0.0) * TIME_BONUS_PER_SECOND).round() as u32
+}
+
+fn reset_run_score_system(mut run_score: ResMut<RunScore>) {
+    *run_score = RunScore::default();
+}
+
+fn spawn_points_ui_system(mut commands: Commands, asset_server: Res<AssetServer>) {
+    let font = asset_server.load("papercut.ttf");
+
+    // Sits under the floor count
+    commands.spawn((
+        TextBundle::from_section(
+            "".to_string(),
+            TextStyle {
+                font,
+                font_size: 40.0,
+                color: Color::hex("FFFFFF7F").unwrap(),
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            position: UiRect {
+                top: Val::Percent(27.0),
+                left: Val::Percent(5.0),
+                ..default()
+            },
+            ..default()
+        }),
+        PointsUI,
+        GameplayCleanup,
+    ));
+}
+
+// Scores new floors and collected coins, and takes back what a respawn undoes
+fn run_score_system(
+    mut commands: Commands,
+    mut run_score: ResMut<RunScore>,
+    mut floor_reached_events: EventReader<FloorReachedEvent>,
+    mut coin_collected_events: EventReader<CoinCollectedEvent>,
+    mut respawn_events: EventReader<PlayerRespawnedEvent>,
+    platform_query: Query<(&Platform, &Transform)>,
+    mut coin_query: Query<(Entity, &mut Coin, &Parent)>,
+) {
+    run_score.coins += coin_collected_events.iter().count() as u32;
+
+    // Only the floors still counted after the respawn keep their points, and the coins over the
+    // others can be collected again
+    if respawn_events.iter().last().is_some() {
+        let still_reached = |platform_entity: Entity| {
+            platform_query
+                .get(platform_entity)
+                .is_ok_and(|(platform, _)| platform.already_collided)
+        };
+        run_score
+            .floors
+            .retain(|(platform_entity, _, _)| still_reached(*platform_entity));
+        run_score.multiplier = 1;
+
+        for (coin_entity, mut coin, platform_entity) in coin_query.iter_mut() {
+            if coin.collected && !still_reached(platform_entity.get()) {
+                coin.restore(&mut commands, coin_entity);
+                run_score.coins -= 1;
+            }
+        }
+    }
+
+    for floor_reached in floor_reached_events.iter() {
+        let Ok((_, platform_transform)) = platform_query.get(floor_reached.0) else {
+            continue;
+        };
+        let height = platform_transform.translation.y;
+
+        let skipped = run_score.highest_floor().map_or(0, |highest| {
+            platform_query
+                .iter()
+                .filter(|(_, other_transform)| {
+                    other_transform.translation.y > highest
+                        && other_transform.translation.y < height
+                })
+                .count() as u32
+        });
+        run_score.multiplier = skipped + 1;
+        let points = FLOOR_POINTS * run_score.multiplier;
+        run_score.floors.push((floor_reached.0, height, points));
+    }
+}
+
+fn update_points_ui_system(
+    mut text_query: Query<&mut Text, With<PointsUI>>,
+    run_score: Res<RunScore>,
+) {
+    for mut text in text_query.iter_mut() {
+        text.sections[0].value = if run_score.multiplier > 1 {
+            format!(
+                "SCORE {}  COINS {}  SKIP x{}",
+                run_score.points(),
+                run_score.coins,
+                run_score.multiplier
+            )
+        } else {
+            format!("SCORE {}  COINS {}", run_score.points(), run_score.coins)
+        };
+    }
+}
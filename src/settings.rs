@@ -0,0 +1,234 @@
+use crate::controls_menu::ControlsMenuPlugin;
+use crate::difficulty::Difficulty;
+use crate::input_bindings::InputAction;
+use crate::player::ControlMode;
+use crate::ui::{spawn_menu_button, spawn_menu_root, spawn_menu_row, spawn_menu_title};
+use bevy::prelude::*;
+
+pub struct SettingsPlugin;
+
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub enum SettingsMenuState {
+    Closed,
+    Open,
+    // Pushed on top of "Open" while the key bindings are being changed
+    Controls,
+}
+
+#[derive(Resource)]
+pub struct GameSettings {
+    pub music_volume: f64,
+    // Races the fastest run on the current tower as a translucent player
+    pub ghost_enabled: bool,
+    // Sends lava up the tower after the player
+    pub hazard_enabled: bool,
+    pub control_mode: ControlMode,
+    pub difficulty: Difficulty,
+}
+
+#[derive(Component)]
+struct SettingsMenu;
+
+#[derive(Component, Clone, Copy)]
+enum SettingsButton {
+    MusicVolume,
+    Ghost,
+    Hazard,
+    ControlMode,
+    Difficulty,
+    Controls,
+    Back,
+}
+
+const MUSIC_VOLUME_STEPS: [f64; 5] = [0.0, 0.25, 0.5, 0.75, 0.95];
+
+impl Default for GameSettings {
+    fn default() -> Self {
+        GameSettings {
+            music_volume: 0.95,
+            ghost_enabled: true,
+            hazard_enabled: false,
+            control_mode: ControlMode::AutoBounce,
+            difficulty: Difficulty::Normal,
+        }
+    }
+}
+
+impl SettingsButton {
+    fn label(&self, settings: &GameSettings) -> String {
+        match self {
+            SettingsButton::MusicVolume => {
+                format!("MUSIC {}%", (settings.music_volume * 100.0).round())
+            }
+            SettingsButton::Ghost => {
+                if settings.ghost_enabled {
+                    "GHOST ON".to_string()
+                } else {
+                    "GHOST OFF".to_string()
+                }
+            }
+            SettingsButton::Hazard => {
+                if settings.hazard_enabled {
+                    "HAZARD ON".to_string()
+                } else {
+                    "HAZARD OFF".to_string()
+                }
+            }
+            SettingsButton::ControlMode => match settings.control_mode {
+                ControlMode::AutoBounce => "JUMP AUTO".to_string(),
+                ControlMode::ManualJump => "JUMP BUTTON".to_string(),
+            },
+            SettingsButton::Difficulty => format!("DIFFICULTY {}", settings.difficulty.label()),
+            SettingsButton::Controls => "CONTROLS".to_string(),
+            SettingsButton::Back => "BACK".to_string(),
+        }
+    }
+}
+
+impl Plugin for SettingsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<GameSettings>()
+            .add_state(SettingsMenuState::Closed)
+            .add_plugin(ControlsMenuPlugin)
+            .add_system_set(
+                SystemSet::on_enter(SettingsMenuState::Open)
+                    .with_system(spawn_settings_menu_system),
+            )
+            .add_system_set(
+                SystemSet::on_update(SettingsMenuState::Open)
+                    .with_system(settings_button_system)
+                    .with_system(update_settings_labels_system)
+                    .with_system(close_settings_input_system),
+            )
+            .add_system_set(
+                SystemSet::on_exit(SettingsMenuState::Open)
+                    .with_system(despawn_settings_menu_system),
+            )
+            // The settings menu steps aside while the controls menu is open
+            .add_system_set(
+                SystemSet::on_pause(SettingsMenuState::Open)
+                    .with_system(despawn_settings_menu_system),
+            )
+            .add_system_set(
+                SystemSet::on_resume(SettingsMenuState::Open)
+                    .with_system(spawn_settings_menu_system),
+            );
+    }
+}
+
+fn spawn_settings_menu_system(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    settings: Res<GameSettings>,
+) {
+    // The settings menu is drawn on top of whichever menu opened it
+    let font = asset_server.load("papercut.ttf");
+
+    let root = spawn_menu_root(&mut commands, SettingsMenu);
+    commands.entity(root).with_children(|parent| {
+        spawn_menu_title(parent, &font, "SETTINGS");
+        spawn_menu_button(
+            parent,
+            &font,
+            &SettingsButton::MusicVolume.label(&settings),
+            SettingsButton::MusicVolume,
+        );
+        // The toggles share a row so the menu still fits the screen
+        spawn_menu_row(parent).with_children(|row| {
+            for button in [SettingsButton::Ghost, SettingsButton::Hazard] {
+                spawn_menu_button(row, &font, &button.label(&settings), button);
+            }
+        });
+        for button in [
+            SettingsButton::ControlMode,
+            SettingsButton::Difficulty,
+            SettingsButton::Controls,
+            SettingsButton::Back,
+        ] {
+            spawn_menu_button(parent, &font, &button.label(&settings), button);
+        }
+    });
+}
+
+fn settings_button_system(
+    button_query: Query<(&Interaction, &SettingsButton), Changed<Interaction>>,
+    mut settings: ResMut<GameSettings>,
+    mut settings_menu_state: ResMut<State<SettingsMenuState>>,
+) {
+    for (interaction, button) in button_query.iter() {
+        if *interaction != Interaction::Clicked {
+            continue;
+        }
+
+        match button {
+            SettingsButton::MusicVolume => {
+                // Step up through the volume levels and wrap back around to silence
+                settings.music_volume = MUSIC_VOLUME_STEPS
+                    .iter()
+                    .copied()
+                    .find(|step| *step > settings.music_volume + f64::EPSILON)
+                    .unwrap_or(MUSIC_VOLUME_STEPS[0]);
+            }
+            SettingsButton::Ghost => {
+                settings.ghost_enabled = !settings.ghost_enabled;
+            }
+            SettingsButton::Hazard => {
+                settings.hazard_enabled = !settings.hazard_enabled;
+            }
+            SettingsButton::ControlMode => {
+                settings.control_mode = match settings.control_mode {
+                    ControlMode::AutoBounce => ControlMode::ManualJump,
+                    ControlMode::ManualJump => ControlMode::AutoBounce,
+                };
+            }
+            SettingsButton::Difficulty => {
+                settings.difficulty = settings.difficulty.next();
+            }
+            SettingsButton::Controls => {
+                let _ = settings_menu_state.push(SettingsMenuState::Controls);
+            }
+            SettingsButton::Back => {
+                let _ = settings_menu_state.set(SettingsMenuState::Closed);
+            }
+        }
+    }
+}
+
+// Keeps the button labels in sync with the current settings
+fn update_settings_labels_system(
+    button_query: Query<(&SettingsButton, &Children)>,
+    mut text_query: Query<&mut Text>,
+    settings: Res<GameSettings>,
+) {
+    if !settings.is_changed() {
+        return;
+    }
+
+    for (button, children) in button_query.iter() {
+        if let Ok(mut text) = text_query.get_mut(children[0]) {
+            text.sections[0].value = button.label(&settings);
+        }
+    }
+}
+
+fn close_settings_input_system(
+    mut keyboard_input: ResMut<Input<KeyCode>>,
+    mut action_input: ResMut<Input<InputAction>>,
+    mut settings_menu_state: ResMut<State<SettingsMenuState>>,
+) {
+    // Consume the key press so the menu underneath does not react to it as well
+    if keyboard_input.clear_just_pressed(KeyCode::Escape) {
+        // Escape may also be bound to pause, which would resume the run underneath
+        action_input.clear_just_pressed(InputAction::Pause);
+        let _ = settings_menu_state.set(SettingsMenuState::Closed);
+    }
+}
+
+fn despawn_settings_menu_system(
+    mut commands: Commands,
+    settings_menu_query: Query<Entity, With<SettingsMenu>>,
+) {
+    for entities in settings_menu_query.iter() {
+        commands.entity(entities).despawn_recursive();
+    }
+}
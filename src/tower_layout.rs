@@ -0,0 +1,213 @@
+use crate::command_line_value;
+use crate::platform_path::PlatformPath;
+use crate::platforms::{PlatformType, Surface};
+use bevy::asset::{AssetLoader, LoadContext, LoadedAsset};
+use bevy::prelude::*;
+use bevy::reflect::TypeUuid;
+use bevy::utils::BoxedFuture;
+use serde::Deserialize;
+use std::fmt;
+
+pub struct TowerLayoutPlugin;
+
+pub const DEFAULT_PLATFORM_TEXTURE: &str = "PlatformTexture2.png";
+
+// A hand-authored tower, loaded from a `.tower.ron` or `.tower.json` file in the assets folder
+#[derive(Deserialize, TypeUuid, Debug, Clone)]
+#[uuid = "5b7c2a4e-9d1f-4c36-8a0b-3e6f1d2c7a94"]
+pub struct TowerLayout {
+    pub floors: Vec<FloorLayout>,
+}
+
+// Describes a single floor of the tower
+#[derive(Deserialize, Debug, Clone)]
+pub struct FloorLayout {
+    pub x: f32,
+    pub y: f32,
+    pub platform_type: PlatformType,
+    #[serde(default)]
+    pub speed: f32,
+    // The left and right limits of a moving platform, defaults to the edges of the window
+    #[serde(default)]
+    pub bounds: Option<(f32, f32)>,
+    #[serde(default)]
+    pub texture: Option<String>,
+    // Seconds a crumbling platform holds once landed on, defaults to `DEFAULT_CRUMBLE_DELAY`
+    #[serde(default)]
+    pub crumble_delay: Option<f32>,
+    #[serde(default)]
+    pub surface: Surface,
+    // The route of a path platform
+    #[serde(default)]
+    pub path: Option<PlatformPath>,
+    // Lets the player jump up through the platform and drop down through it
+    #[serde(default)]
+    pub one_way: bool,
+    // Defaults to `PLATFORM_WIDTH`
+    #[serde(default)]
+    pub width: Option<f32>,
+    // Falling after reaching the floor comes back to it while the player has lives left
+    #[serde(default)]
+    pub checkpoint: bool,
+    // A coin floating at this offset from the floor's position
+    #[serde(default)]
+    pub coin: Option<(f32, f32)>,
+}
+
+// The handle of the layout requested with `--level <path>`, or `None` for a procedural tower
+#[derive(Resource, Default)]
+pub struct TowerLayoutHandle(pub Option<Handle<TowerLayout>>);
+
+#[derive(Debug)]
+pub enum TowerLayoutError {
+    Ron(ron::error::SpannedError),
+    Json(serde_json::Error),
+    NoFloors,
+    InvalidBounds { floor: usize, left: f32, right: f32 },
+    InvalidSpeed { floor: usize, speed: f32 },
+    InvalidCrumbleDelay { floor: usize, delay: f32 },
+    InvalidConveyorSpeed { floor: usize, speed: f32 },
+    InvalidPath { floor: usize },
+    InvalidWidth { floor: usize, width: f32 },
+}
+
+#[derive(Default)]
+pub struct TowerLayoutLoader;
+
+impl Plugin for TowerLayoutPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_asset::<TowerLayout>()
+            .init_asset_loader::<TowerLayoutLoader>()
+            .init_resource::<TowerLayoutHandle>()
+            .add_startup_system(load_tower_layout_system);
+    }
+}
+
+impl TowerLayout {
+    // Parses and validates a layout, so a malformed file is rejected before anything is spawned
+    pub fn from_bytes(bytes: &[u8], is_json: bool) -> Result<Self, TowerLayoutError> {
+        let layout: TowerLayout = if is_json {
+            serde_json::from_slice(bytes).map_err(TowerLayoutError::Json)?
+        } else {
+            ron::de::from_bytes(bytes).map_err(TowerLayoutError::Ron)?
+        };
+
+        if layout.floors.is_empty() {
+            return Err(TowerLayoutError::NoFloors);
+        }
+
+        for (floor, floor_layout) in layout.floors.iter().enumerate() {
+            if let Some((left, right)) = floor_layout.bounds {
+                if left >= right {
+                    return Err(TowerLayoutError::InvalidBounds { floor, left, right });
+                }
+            }
+            if floor_layout.speed < 0.0
+                || (floor_layout.platform_type == PlatformType::Moving && floor_layout.speed == 0.0)
+            {
+                return Err(TowerLayoutError::InvalidSpeed {
+                    floor,
+                    speed: floor_layout.speed,
+                });
+            }
+            if let Some(delay) = floor_layout.crumble_delay {
+                if delay <= 0.0 {
+                    return Err(TowerLayoutError::InvalidCrumbleDelay { floor, delay });
+                }
+            }
+            if floor_layout.platform_type == PlatformType::Path
+                && !floor_layout
+                    .path
+                    .as_ref()
+                    .is_some_and(PlatformPath::is_valid)
+            {
+                return Err(TowerLayoutError::InvalidPath { floor });
+            }
+            if let Some(width) = floor_layout.width.filter(|width| *width <= 0.0) {
+                return Err(TowerLayoutError::InvalidWidth { floor, width });
+            }
+            if let Surface::Conveyor { speed } = floor_layout.surface {
+                if speed == 0.0 || !speed.is_finite() {
+                    return Err(TowerLayoutError::InvalidConveyorSpeed { floor, speed });
+                }
+            }
+        }
+
+        Ok(layout)
+    }
+}
+
+impl fmt::Display for TowerLayoutError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TowerLayoutError::Ron(err) => write!(f, "malformed tower layout: {}", err),
+            TowerLayoutError::Json(err) => write!(f, "malformed tower layout: {}", err),
+            TowerLayoutError::NoFloors => write!(f, "tower layout has no floors"),
+            TowerLayoutError::InvalidBounds { floor, left, right } => write!(
+                f,
+                "floor {} has a left bound ({}) that is not less than its right bound ({})",
+                floor, left, right
+            ),
+            TowerLayoutError::InvalidSpeed { floor, speed } => write!(
+                f,
+                "floor {} has an invalid speed ({}), moving platforms need a positive speed",
+                floor, speed
+            ),
+            TowerLayoutError::InvalidCrumbleDelay { floor, delay } => write!(
+                f,
+                "floor {} has an invalid crumble delay ({}), it needs to be positive",
+                floor, delay
+            ),
+            TowerLayoutError::InvalidConveyorSpeed { floor, speed } => write!(
+                f,
+                "floor {} has a conveyor that does not move ({}), it needs a non-zero speed",
+                floor, speed
+            ),
+            TowerLayoutError::InvalidPath { floor } => write!(
+                f,
+                "floor {} needs a path with at least two distinct waypoints and a positive speed",
+                floor
+            ),
+            TowerLayoutError::InvalidWidth { floor, width } => write!(
+                f,
+                "floor {} has an invalid width ({}), it needs to be positive",
+                floor, width
+            ),
+        }
+    }
+}
+
+impl std::error::Error for TowerLayoutError {}
+
+impl AssetLoader for TowerLayoutLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), bevy::asset::Error>> {
+        Box::pin(async move {
+            let is_json = load_context
+                .path()
+                .extension()
+                .is_some_and(|extension| extension == "json");
+            let layout = TowerLayout::from_bytes(bytes, is_json)?;
+            load_context.set_default_asset(LoadedAsset::new(layout));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["tower.ron", "tower.json"]
+    }
+}
+
+// Starts loading the layout passed with `--level <path>`, relative to the assets folder
+fn load_tower_layout_system(
+    mut tower_layout_handle: ResMut<TowerLayoutHandle>,
+    asset_server: Res<AssetServer>,
+) {
+    if let Some(path) = command_line_value("--level") {
+        info!("Loading tower layout {}", path);
+        tower_layout_handle.0 = Some(asset_server.load(path.as_str()));
+    }
+}
@@ -1,4 +1,5 @@
-use crate::{GameplayStateSubstates, Player};
+use crate::{GameplayCleanup, GameplayStateSubstates, Player};
+use bevy::ecs::system::EntityCommands;
 use bevy::prelude::*;
 
 pub struct UIPlugin;
@@ -6,17 +7,22 @@ pub struct UIPlugin;
 #[derive(Component)]
 struct ScoreUI;
 
+pub const MENU_BUTTON_COLOR: Color = Color::rgba(1.0, 1.0, 1.0, 0.08);
+pub const MENU_BUTTON_HOVERED_COLOR: Color = Color::rgba(1.0, 1.0, 1.0, 0.2);
+pub const MENU_BUTTON_PRESSED_COLOR: Color = Color::rgba(1.0, 1.0, 1.0, 0.35);
+
 impl Plugin for UIPlugin {
     fn build(&self, app: &mut App) {
-        app.add_system_set(
-            SystemSet::on_enter(GameplayStateSubstates::PreGame)
-                .with_system(spawn_black_bars_system)
-                .with_system(spawn_score_ui_system),
-        )
-        .add_system_set(
-            SystemSet::on_update(GameplayStateSubstates::DuringGame)
-                .with_system(update_score_ui_system),
-        );
+        app.add_system(menu_button_color_system)
+            .add_system_set(
+                SystemSet::on_enter(GameplayStateSubstates::PreGame)
+                    .with_system(spawn_black_bars_system)
+                    .with_system(spawn_score_ui_system),
+            )
+            .add_system_set(
+                SystemSet::on_update(GameplayStateSubstates::DuringGame)
+                    .with_system(update_score_ui_system),
+            );
     }
 }
 
@@ -24,13 +30,16 @@ fn spawn_black_bars_system(mut commands: Commands, _asset_server: Res<AssetServe
     // This function spawns two black bars as children of a root node in the UI.
     // The bars are positioned at the top and bottom of the screen and take up 10% of the screen height each.
     let root = commands
-        .spawn(NodeBundle {
-            style: Style {
-                size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                    ..default()
+                },
                 ..default()
             },
-            ..default()
-        })
+            GameplayCleanup,
+        ))
         .id();
 
     let top_bar = commands
@@ -94,6 +103,7 @@ fn spawn_score_ui_system(mut commands: Commands, asset_server: Res<AssetServer>)
             ..default()
         }),
         ScoreUI,
+        GameplayCleanup,
     ));
 }
 
@@ -103,9 +113,140 @@ fn update_score_ui_system(
 ) {
     // This function updates the text of a text entity in the UI with the player's current score.
     // The text entity is identified using the `ScoreUI` component.
-    
+
     let player = player_query.single();
     for mut text in text_query.iter_mut() {
         text.sections[0].value = player.score.to_string();
     }
 }
+
+// Spawns a full screen, semi-transparent node that centers a column of menu items
+pub fn spawn_menu_root(commands: &mut Commands, marker: impl Component) -> Entity {
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                    flex_direction: FlexDirection::Column,
+                    justify_content: JustifyContent::Center,
+                    align_items: AlignItems::Center,
+                    ..default()
+                },
+                background_color: Color::rgba(0.0, 0.0, 0.0, 0.6).into(),
+                ..default()
+            },
+            marker,
+        ))
+        .id()
+}
+
+// Spawns a large heading in the papercut font used by the rest of the UI
+pub fn spawn_menu_title(parent: &mut ChildBuilder, font: &Handle<Font>, title: &str) {
+    parent.spawn(
+        TextBundle::from_section(
+            title.to_string(),
+            TextStyle {
+                font: font.clone(),
+                font_size: 90.0,
+                color: Color::hex("FFFFFFE6").unwrap(),
+            },
+        )
+        .with_style(Style {
+            margin: UiRect {
+                bottom: Val::Px(24.0),
+                ..default()
+            },
+            ..default()
+        }),
+    );
+}
+
+// Spawns a line of plain text, used for the information shown between titles and buttons
+pub fn spawn_menu_text(parent: &mut ChildBuilder, font: &Handle<Font>, text: &str) {
+    parent.spawn(
+        TextBundle::from_section(
+            text.to_string(),
+            TextStyle {
+                font: font.clone(),
+                font_size: 36.0,
+                color: Color::hex("FFFFFFBF").unwrap(),
+            },
+        )
+        .with_style(Style {
+            margin: UiRect::all(Val::Px(4.0)),
+            ..default()
+        }),
+    );
+}
+
+// Spawns a node that lays its children out side by side, for menus too long for a single column
+pub fn spawn_menu_row<'w, 's, 'a>(
+    parent: &'a mut ChildBuilder<'w, 's, '_>,
+) -> EntityCommands<'w, 's, 'a> {
+    parent.spawn(NodeBundle {
+        style: Style {
+            flex_direction: FlexDirection::Row,
+            ..default()
+        },
+        ..default()
+    })
+}
+
+// Spawns a clickable button whose label is its first child
+pub fn spawn_menu_button(
+    parent: &mut ChildBuilder,
+    font: &Handle<Font>,
+    label: &str,
+    button: impl Component,
+) {
+    parent
+        .spawn((
+            ButtonBundle {
+                style: Style {
+                    // Buttons grow past their usual width when the label needs it
+                    size: Size::new(Val::Auto, Val::Px(56.0)),
+                    min_size: Size::new(Val::Px(360.0), Val::Auto),
+                    margin: UiRect::all(Val::Px(6.0)),
+                    padding: UiRect {
+                        left: Val::Px(16.0),
+                        right: Val::Px(16.0),
+                        ..default()
+                    },
+                    justify_content: JustifyContent::Center,
+                    align_items: AlignItems::Center,
+                    ..default()
+                },
+                background_color: MENU_BUTTON_COLOR.into(),
+                ..default()
+            },
+            button,
+        ))
+        .with_children(|button_parent| {
+            button_parent.spawn(TextBundle::from_section(
+                label.to_string(),
+                TextStyle {
+                    font: font.clone(),
+                    font_size: 40.0,
+                    color: Color::WHITE,
+                },
+            ));
+        });
+}
+
+// Highlights every menu button while it is hovered or pressed
+#[allow(clippy::type_complexity)]
+fn menu_button_color_system(
+    mut button_query: Query<
+        (&Interaction, &mut BackgroundColor),
+        (Changed<Interaction>, With<Button>),
+    >,
+) {
+    for (interaction, mut background_color) in button_query.iter_mut() {
+        *background_color = match *interaction {
+            Interaction::Clicked => MENU_BUTTON_PRESSED_COLOR.into(),
+            Interaction::Hovered => MENU_BUTTON_HOVERED_COLOR.into(),
+            Interaction::None => MENU_BUTTON_COLOR.into(),
+        };
+    }
+}
@@ -1,3 +1,4 @@
+use crate::{AppState, GameplayStateSubstates};
 use bevy::prelude::*;
 
 pub struct GameWindowPlugin;
@@ -15,15 +16,29 @@ impl Plugin for GameWindowPlugin {
             width: 960.0,
             height: 540.0,
         })
-        .add_startup_system(setup_window_settings_system.at_start())
-        .add_system(bevy::window::close_on_esc);
+        .add_system(cursor_capture_system);
     }
 }
 
-pub fn setup_window_settings_system(mut windows: ResMut<Windows>) {
-    // This code sets up the window settings for the primary window in the `windows` resource.
-    // It makes the cursor invisible and locks it to the window.
-    let window = windows.get_primary_mut().unwrap();
-    window.set_cursor_visibility(false);
-    window.set_cursor_grab_mode(bevy::window::CursorGrabMode::Locked);
+pub fn cursor_capture_system(
+    mut windows: ResMut<Windows>,
+    app_state: Res<State<AppState>>,
+    gameplay_substate: Res<State<GameplayStateSubstates>>,
+) {
+    // This code sets up the cursor for the primary window in the `windows` resource.
+    // It makes the cursor invisible and locks it to the window during play, and releases it so menus can be clicked.
+    let captured = *app_state.current() == AppState::Gameplay
+        && *gameplay_substate.current() != GameplayStateSubstates::Paused;
+
+    let Some(window) = windows.get_primary_mut() else {
+        return;
+    };
+    if window.cursor_visible() == captured {
+        window.set_cursor_visibility(!captured);
+        window.set_cursor_grab_mode(if captured {
+            bevy::window::CursorGrabMode::Locked
+        } else {
+            bevy::window::CursorGrabMode::None
+        });
+    }
 }